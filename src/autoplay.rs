@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use crate::{
+    json::{Json, ToJson},
+    note::{NoteType, Slide},
+    timing::TempoMap,
+    ChuniChart,
+};
+
+/// How long a Tap, ExTap or Flick is touched for, in milliseconds.
+const TAP_LENGTH: f64 = 50.0;
+/// How long the hands take to pass through the air sensor for an air note,
+/// in milliseconds.
+const AIR_LENGTH: f64 = 50.0;
+/// How long a cell is released for before it is touched again for the next
+/// note, in milliseconds.
+const RELEASE: f64 = 16.0;
+/// The amount of steps a measure is split into when following a Slide.
+const SLIDE_STEPS: usize = 64;
+
+/// What has to happen in the air sensor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AirInput {
+    /// Nothing is in the air sensor.
+    #[default]
+    Idle,
+    /// The hands are kept in the air sensor, for AirHolds, AirSlides and
+    /// AirCrushes.
+    Held,
+    /// The hands move down through the air sensor, for air down notes.
+    Falling,
+    /// The hands move up through the air sensor, for air up notes.
+    Rising,
+}
+
+/// The input of a perfect play during a span of time.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InputFrame {
+    /// The start of the span, in milliseconds from the start of the chart.
+    pub start: f64,
+    /// The end of the span, in milliseconds from the start of the chart.
+    pub end: f64,
+    /// The touch pads that are held, one bit per pad. Every cell has two
+    /// pads, the top one at bit `2 * cell` and the bottom one at bit
+    /// `2 * cell + 1`, with cell 0 being the leftmost column.
+    pub pads: u32,
+    pub air: AirInput,
+}
+
+impl InputFrame {
+    /// Whether or not the given cell of the slider is held.
+    pub fn is_held(&self, cell: usize) -> bool {
+        cell < 16 && self.pads & (0b11 << (2 * cell)) != 0
+    }
+
+    /// The cells of the slider that are held, from left to right.
+    pub fn cells(&self) -> Vec<usize> {
+        (0..16).filter(|cell| self.is_held(*cell)).collect()
+    }
+}
+
+/// A cell touched from `start` until `end`, in milliseconds.
+struct Press {
+    cell: usize,
+    start: f64,
+    end: f64,
+}
+
+impl NoteType {
+    /// The note as a [`Slide`], if it is a segment of one.
+    pub(crate) fn slide(&self) -> Option<&Slide> {
+        match self {
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => Some(note),
+            _ => None,
+        }
+    }
+}
+
+impl ChuniChart {
+    /// The position, cell and width every Slide segment ends at. Segments of
+    /// a slide chain start where the previous one ends, and are not hit
+    /// again.
+    pub(crate) fn slide_ends(&self) -> HashSet<(usize, usize, usize)> {
+        self.notes
+            .iter()
+            .filter_map(|note| Some((note.tick(self.resolution), note.slide()?)))
+            .map(|(tick, slide)| (tick + slide.duration, slide.end_cell, slide.end_width))
+            .collect()
+    }
+
+    /// Returns the presses needed to play the ground notes of the chart, and
+    /// the times notes are hit at, for every cell.
+    fn presses(&self, tempo: &TempoMap) -> (Vec<Press>, Vec<Vec<f64>>) {
+        let resolution = self.resolution;
+        let mut presses = vec![];
+        let mut heads = vec![vec![]; 16];
+
+        let slide_ends = self.slide_ends();
+
+        for note in &self.notes {
+            let tick = note.tick(resolution);
+            let ms = tempo.ms(tick);
+            let (cell, width) = (note.cell(), note.width());
+
+            let (is_head, end) = match note {
+                NoteType::Tap(_) | NoteType::ExTap(_) | NoteType::Flick(_) => {
+                    (true, ms + TAP_LENGTH)
+                }
+                NoteType::Hold(hold) | NoteType::HoldWithExTapHead(hold) => {
+                    (true, tempo.ms(tick + hold.duration))
+                }
+                NoteType::Slide(slide)
+                | NoteType::SlideControlPoint(slide)
+                | NoteType::SlideWithExTapHead(slide)
+                | NoteType::SlideControlPointWithExTapHead(slide) => {
+                    let duration = slide.duration;
+                    let is_head = !slide_ends.contains(&(tick, cell, width));
+
+                    // follow the slide from its start to its end position, one
+                    // step at a time.
+                    let step = (resolution / SLIDE_STEPS).max(1);
+                    for offset in (0..duration.max(1)).step_by(step) {
+                        let progress = offset as f64 / duration.max(1) as f64;
+                        let lerp = |from: usize, to: usize| {
+                            (from as f64 + (to as f64 - from as f64) * progress).round() as usize
+                        };
+                        let cell = lerp(slide.cell, slide.end_cell);
+                        let width = lerp(slide.width, slide.end_width).max(1);
+                        let (from, to) = (
+                            tempo.ms(tick + offset),
+                            tempo.ms(tick + (offset + step).min(duration.max(1))),
+                        );
+
+                        for cell in cell..(cell + width).min(16) {
+                            presses.push(Press {
+                                cell,
+                                start: from,
+                                end: to,
+                            });
+                        }
+                    }
+
+                    if is_head {
+                        for heads in heads.iter_mut().take(cell + width).skip(cell) {
+                            heads.push(ms);
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            for (cell, heads) in heads.iter_mut().enumerate().take(cell + width).skip(cell) {
+                presses.push(Press {
+                    cell,
+                    start: ms,
+                    end,
+                });
+                if is_head {
+                    heads.push(ms);
+                }
+            }
+        }
+
+        for heads in &mut heads {
+            heads.sort_by(f64::total_cmp);
+        }
+
+        (presses, heads)
+    }
+
+    /// Generates the input of a perfect play of the chart, as consecutive
+    /// spans of time from the start of the chart to the end of the last
+    /// note.
+    ///
+    /// Taps, ExTaps and Flicks are touched briefly, Holds are held until
+    /// their end, and Slides are followed from their start position to their
+    /// end position. A cell is always released shortly before it has to be
+    /// hit again. Air notes move the hands through the air sensor, and
+    /// AirHolds, AirSlides and AirCrushes keep them there. Mines are never
+    /// touched.
+    pub fn autoplay(&self) -> Vec<InputFrame> {
+        let tempo = self.tempo_map();
+        let (presses, heads) = self.presses(&tempo);
+
+        // (time, pads, air, whether the input starts or stops)
+        let mut events: Vec<(f64, u32, AirInput, bool)> = vec![];
+
+        for press in presses {
+            let next = heads[press.cell]
+                .iter()
+                .find(|head| **head > press.start)
+                .copied();
+            let end = match next {
+                Some(next) if next - RELEASE <= press.start => {
+                    press.end.min((press.start + next) / 2.0)
+                }
+                Some(next) => press.end.min(next - RELEASE),
+                None => press.end,
+            };
+
+            if end > press.start {
+                let pads = 0b11 << (2 * press.cell);
+                events.push((press.start, pads, AirInput::Idle, true));
+                events.push((end, pads, AirInput::Idle, false));
+            }
+        }
+
+        for note in &self.notes {
+            let tick = note.tick(self.resolution);
+            let ms = tempo.ms(tick);
+
+            let (air, end) = match note {
+                NoteType::Air(_) | NoteType::AirUpLeft(_) | NoteType::AirUpRight(_) => {
+                    (AirInput::Rising, ms + AIR_LENGTH)
+                }
+                NoteType::AirDown(_) | NoteType::AirDownLeft(_) | NoteType::AirDownRight(_) => {
+                    (AirInput::Falling, ms + AIR_LENGTH)
+                }
+                NoteType::AirHold(_)
+                | NoteType::AirSlide(_)
+                | NoteType::AirSlideControlPoint(_)
+                | NoteType::AirCrush(_) => (
+                    AirInput::Held,
+                    tempo.ms(tick + note.duration().unwrap_or_default()),
+                ),
+                _ => continue,
+            };
+
+            if end > ms {
+                events.push((ms, 0, air, true));
+                events.push((end, 0, air, false));
+            }
+        }
+
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut frames: Vec<InputFrame> = vec![];
+        let mut pads = [0usize; 32];
+        let mut air = [0usize; 4];
+        let mut time = 0.0;
+
+        let mut events = events.into_iter().peekable();
+        while let Some((at, _, _, _)) = events.peek().copied() {
+            if at > time {
+                let frame = InputFrame {
+                    start: time,
+                    end: at,
+                    pads: (0..32)
+                        .filter(|pad| pads[*pad] > 0)
+                        .fold(0, |bits, pad| bits | 1 << pad),
+                    air: [
+                        AirInput::Rising,
+                        AirInput::Falling,
+                        AirInput::Held,
+                        AirInput::Idle,
+                    ]
+                    .into_iter()
+                    .find(|x| *x == AirInput::Idle || air[*x as usize] > 0)
+                    .unwrap(),
+                };
+
+                match frames.last_mut() {
+                    Some(last) if last.pads == frame.pads && last.air == frame.air => {
+                        last.end = frame.end
+                    }
+                    _ => frames.push(frame),
+                }
+                time = at;
+            }
+
+            while let Some((_, bits, input, start)) = events.next_if(|x| x.0 == at) {
+                let counter = |count: &mut usize| match start {
+                    true => *count += 1,
+                    false => *count -= 1,
+                };
+
+                (0..32)
+                    .filter(|pad| bits & 1 << pad != 0)
+                    .for_each(|pad| counter(&mut pads[pad]));
+                if input != AirInput::Idle {
+                    counter(&mut air[input as usize]);
+                }
+            }
+        }
+
+        frames
+    }
+}
+
+impl ToJson for InputFrame {
+    fn to_json(&self) -> Json {
+        let air = match self.air {
+            AirInput::Idle => "idle",
+            AirInput::Held => "held",
+            AirInput::Falling => "falling",
+            AirInput::Rising => "rising",
+        };
+
+        Json::object([
+            ("start", self.start.into()),
+            ("end", self.end.into()),
+            ("pads", (self.pads as usize).into()),
+            ("air", air.into()),
+        ])
+    }
+}
@@ -0,0 +1,652 @@
+use std::{
+    cell::Cell,
+    env, fs,
+    io::{self, Write},
+    panic::{self, UnwindSafe},
+    path::Path,
+    process::ExitCode,
+    string::ParseError,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+mod view;
+
+use nai_rs::{
+    click::{self, ClickOptions},
+    difficulty::LevelEstimator,
+    format,
+    gimmicks::GimmickOptions,
+    hands::Hand,
+    json::{Json, ToJson},
+    judge::{InputEvent, TimingWindows},
+    lint::{Lint, Severity},
+    music_xml::MusicInfo,
+    score::Rank,
+    span::SourceMap,
+    transform, ugc,
+    version::{ChartVersion, Version},
+    ChuniChart,
+};
+
+const USAGE: &str = "\
+usage: nai <command> <file> [options]
+
+commands:
+    info                     show the chart header, BPM range and note counts
+    lint                     check the chart for common mistakes
+    stats                    show detailed note statistics
+    convert --to <format>    convert the chart to ugc, json or c2s
+    mirror                   flip the chart horizontally
+    random [--mode <mode>]   shuffle the lanes with random, full or s (random by default)
+    fmt [--check]            rewrite the chart in the canonical .c2s layout
+    view                     play the chart back in the terminal
+    click -o <file>          write a metronome and hitsound track as WAV
+    autoplay                 list the slider and air sensor input of a perfect play
+    judge --input <log>      judge a recorded input log and show the score
+    patterns                 list jacks, trills, stairs, chords and other patterns
+    hands                    assign ground notes to hands and list where a third is needed
+    gimmicks                 list scroll speed changes, stops and reverses, and notes too fast to read
+
+options:
+    --json                   print machine readable output (all but view, click and fmt)
+    -o, --output <file>      write the output to a file instead of stdout
+    --check                  only check whether the chart is formatted (fmt)
+    --input <file>           the input log to judge, one event per line (judge)
+    --seed <n>               the seed of the shuffle, random if not given (random)
+    --target <version>       upgrade or downgrade the chart to a VERSION, like 1.12.00 (convert)
+";
+
+const COMMANDS: [&str; 14] = [
+    "info", "lint", "stats", "convert", "mirror", "random", "fmt", "view", "click", "autoplay",
+    "judge", "patterns", "hands", "gimmicks",
+];
+
+struct Args {
+    command: String,
+    file: String,
+    json: bool,
+    check: bool,
+    to: Option<String>,
+    output: Option<String>,
+    input: Option<String>,
+    mode: Option<String>,
+    seed: Option<u64>,
+    target: Option<Version>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = env::args().skip(1);
+        let mut positional = vec![];
+        let mut json = false;
+        let mut check = false;
+        let mut to = None;
+        let mut output = None;
+        let mut input = None;
+        let mut mode = None;
+        let mut seed = None;
+        let mut target = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => json = true,
+                "--check" => check = true,
+                "--to" => to = Some(args.next().ok_or("missing value for --to")?),
+                "-o" | "--output" => {
+                    output = Some(args.next().ok_or("missing value for --output")?)
+                }
+                "--input" => input = Some(args.next().ok_or("missing value for --input")?),
+                "--mode" => mode = Some(args.next().ok_or("missing value for --mode")?),
+                "--seed" => {
+                    let value = args.next().ok_or("missing value for --seed")?;
+                    seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid seed {}", value))?,
+                    );
+                }
+                "--target" => {
+                    let value = args.next().ok_or("missing value for --target")?;
+                    let valid = value.split('.').count() == 3
+                        && value.split('.').all(|x| x.parse::<usize>().is_ok());
+                    if !valid {
+                        return Err(format!("invalid version {}", value));
+                    }
+                    target = Some(value.parse().unwrap());
+                }
+                "-h" | "--help" => return Err(String::new()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let command = positional.next().ok_or("missing command")?;
+        let file = positional.next().ok_or("missing file")?;
+        if let Some(arg) = positional.next() {
+            return Err(format!("unexpected argument {}", arg));
+        }
+
+        Ok(Args {
+            command,
+            file,
+            json,
+            check,
+            to,
+            output,
+            input,
+            mode,
+            seed,
+            target,
+        })
+    }
+}
+
+thread_local! {
+    /// Whether or not the thread is parsing a file in [`load`], which reports
+    /// parse errors itself.
+    static PARSING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn main() -> ExitCode {
+    // parse errors are reported by `load`, so keep their panic messages quiet.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !PARSING.get() {
+            hook(info)
+        }
+    }));
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("error: {}\n", e);
+            }
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if !COMMANDS.contains(&args.command.as_str()) {
+        eprintln!("error: unknown command {}\n", args.command);
+        eprint!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    if args.command == "fmt" {
+        return match fmt(&args) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("error: {}: {}", args.file, e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let (chart, spans) = match load(&args.file, ChuniChart::parse_with_spans) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: {}: {}", args.file, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = match args.command.as_str() {
+        "info" => info(&chart, &spans, &args),
+        "lint" => lint(&chart, &spans, &args),
+        "stats" => stats(&chart, &args),
+        "convert" => convert(chart, &args),
+        "mirror" => mirror(chart, &args),
+        "random" => random(chart, &args),
+        "view" => view::view(&chart).map(|_| true),
+        "click" => click(&chart, &args),
+        "autoplay" => autoplay(&chart, &args),
+        "judge" => judge(&chart, &args),
+        "patterns" => patterns(&chart, &args),
+        "hands" => hands(&chart, &args),
+        "gimmicks" => gimmicks(&chart, &args),
+        _ => unreachable!(),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reads and parses a chart, turning a panic in the parser into an error
+/// message.
+fn load<T>(
+    file: &str,
+    parse: impl FnOnce(String) -> Result<T, ParseError> + UnwindSafe,
+) -> Result<T, String> {
+    let input = fs::read_to_string(file).map_err(|e| e.to_string())?;
+
+    PARSING.set(true);
+    let result = panic::catch_unwind(|| parse(input));
+    PARSING.set(false);
+
+    match result {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(payload) => Err(payload
+            .downcast_ref::<&str>()
+            .map(|x| x.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "could not parse chart".to_string())),
+    }
+}
+
+/// Writes `content` to the output file, or to stdout if there is none.
+fn output(args: &Args, content: &str) -> io::Result<()> {
+    match &args.output {
+        Some(path) => fs::write(path, content),
+        None => io::stdout().write_all(content.as_bytes()),
+    }
+}
+
+fn info(chart: &ChuniChart, spans: &SourceMap, args: &Args) -> io::Result<bool> {
+    let stats = chart.statistics();
+    // charts usually leave DIFFICULT at 00, so only name the difficulty when
+    // the chart has the line, or a Music.xml next to it lists the file.
+    let music = fs::read_to_string(Path::new(&args.file).with_file_name("Music.xml"))
+        .ok()
+        .and_then(|x| x.parse::<MusicInfo>().ok());
+    let (difficult, difficulty) = match music.as_ref().and_then(|x| x.fumen(&args.file)) {
+        Some(fumen) => (fumen.difficulty, fumen.kind()),
+        None if spans.headers.iter().any(|(tag, _)| *tag == "DIFFICULT") => {
+            (chart.difficult, chart.difficulty())
+        }
+        None => (chart.difficult, None),
+    };
+    let lowest = chart.bpm.iter().map(|x| x.bpm).fold(f64::MAX, f64::min);
+    let highest = chart.bpm.iter().map(|x| x.bpm).fold(f64::MIN, f64::max);
+    let (lowest, highest) = if chart.bpm.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (lowest, highest)
+    };
+
+    if args.json {
+        let json = Json::object([
+            ("version", chart.version.first.to_string().into()),
+            ("creator", chart.creator.as_str().into()),
+            ("difficult", difficult.into()),
+            ("difficulty", difficulty.map(|x| x.to_string()).into()),
+            ("level", chart.level.into()),
+            ("resolution", chart.resolution.into()),
+            ("starting_bpm", chart.bpm_def.starting_bpm.into()),
+            ("lowest_bpm", lowest.into()),
+            ("highest_bpm", highest.into()),
+            ("notes", stats.total().into()),
+            ("statistics", stats.to_json()),
+        ]);
+        return output(args, &format!("{:#}\n", json)).map(|_| true);
+    }
+
+    let mut out = String::new();
+    out += &format!("version:    {}\n", chart.version.first);
+    out += &format!("creator:    {}\n", chart.creator);
+    match difficulty {
+        Some(difficulty) => out += &format!("difficult:  {:02} ({})\n", difficult, difficulty),
+        None => out += &format!("difficult:  {:02}\n", difficult),
+    }
+    out += &format!("level:      {:.1}\n", chart.level);
+    out += &format!("resolution: {}\n", chart.resolution);
+    out += &format!(
+        "bpm:        {:.3} ({:.3}-{:.3})\n",
+        chart.bpm_def.starting_bpm, lowest, highest
+    );
+    out += &format!("notes:      {}\n", stats.total());
+    out += &format!("  tap:      {}\n", stats.taps);
+    out += &format!("  extap:    {}\n", stats.ex_taps);
+    out += &format!("  flick:    {}\n", stats.flicks);
+    out += &format!("  mine:     {}\n", stats.mines);
+    out += &format!("  hold:     {}\n", stats.holds);
+    out += &format!("  slide:    {}\n", stats.slides);
+    out += &format!("  air:      {}\n", stats.airs);
+    out += &format!("  air hold: {}\n", stats.air_holds);
+
+    output(args, &out).map(|_| true)
+}
+
+fn lint(chart: &ChuniChart, spans: &SourceMap, args: &Args) -> io::Result<bool> {
+    let lints = chart.lint();
+    let ok = lints.iter().all(|lint| lint.severity != Severity::Error);
+    let span = |lint: &Lint| spans.get(lint.source);
+
+    if args.json {
+        let lints = lints
+            .iter()
+            .map(|lint| match (lint.to_json(), span(lint)) {
+                (Json::Object(mut fields), Some(span)) => {
+                    fields.push(("line".to_string(), span.line.into()));
+                    Json::Object(fields)
+                }
+                (json, _) => json,
+            })
+            .collect::<Vec<_>>();
+        output(args, &format!("{:#}\n", Json::Array(lints)))?;
+    } else {
+        let out = lints
+            .iter()
+            .map(|lint| match span(lint) {
+                Some(span) => format!("{}:{}: {}\n", args.file, span.line, lint),
+                None => format!("{}: {}\n", args.file, lint),
+            })
+            .collect::<String>();
+        output(args, &out)?;
+    }
+
+    Ok(ok)
+}
+
+fn stats(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let stats = chart.statistics();
+    let density = chart.density(1000.0);
+    let features = chart.features();
+    let level = LevelEstimator::default().estimate(&features);
+
+    if args.json {
+        let json = Json::object([
+            ("statistics", stats.to_json()),
+            ("density", density.to_json()),
+            ("progress", chart.progress().to_vec().into()),
+            ("features", features.to_json()),
+            ("estimated_level", level.into()),
+        ]);
+        return output(args, &format!("{:#}\n", json)).map(|_| true);
+    }
+
+    let mut out = String::new();
+    out += &format!("tap:             {}\n", stats.taps);
+    out += &format!("extap:           {}\n", stats.ex_taps);
+    out += &format!("flick:           {}\n", stats.flicks);
+    out += &format!("mine:            {}\n", stats.mines);
+    out += &format!("hold:            {}\n", stats.holds);
+    out += &format!("slide:           {}\n", stats.slides);
+    out += &format!("air:             {}\n", stats.airs);
+    out += &format!("air hold:        {}\n", stats.air_holds);
+    out += &format!("air crush:       {}\n", stats.air_crushes);
+    out += &format!("total:           {}\n", stats.total());
+    out += &format!("hold length:     {}\n", stats.hold_length);
+    out += &format!("slide length:    {}\n", stats.slide_length);
+    out += &format!("air hold length: {}\n", stats.air_hold_length);
+    out += &format!("first tick:      {}\n", stats.first_tick);
+    out += &format!("last tick:       {}\n", stats.last_tick);
+    for (animation, count) in &stats.animations {
+        out += &format!("animation {}:    {}\n", animation, count);
+    }
+    out += &format!("average nps:     {:.2}\n", density.average_nps);
+    out += &format!(
+        "peak nps:        {:.2} (at {:.3}s)\n",
+        density.peak_nps,
+        density.peak_ms / 1000.0
+    );
+    out += &format!(
+        "progress:        {}\n",
+        chart.progress().map(|x| x.to_string()).join(" ")
+    );
+    out += &format!("hand travel:     {:.2}\n", features.hand_travel);
+    out += &format!(
+        "simultaneous:    {:.1}%\n",
+        features.simultaneous_rate * 100.0
+    );
+    out += &format!("air ratio:       {:.1}%\n", features.air_ratio * 100.0);
+    out += &format!("estimated level: {:.1}\n", level);
+
+    output(args, &out).map(|_| true)
+}
+
+fn convert(mut chart: ChuniChart, args: &Args) -> io::Result<bool> {
+    if let Some(version) = args.target {
+        for issue in chart.convert_version(ChartVersion::new(version)) {
+            eprintln!("warning: {}: {}", args.file, issue);
+        }
+    }
+
+    match args.to.as_deref() {
+        Some("ugc") => {
+            let stats = chart.statistics();
+            if stats.air_holds + stats.air_crushes > 0 {
+                eprintln!(
+                    "warning: {} air holds, air slides and air crushes cannot be converted yet",
+                    stats.air_holds + stats.air_crushes
+                );
+            }
+
+            for note in ugc::clamped(&chart).into_iter().map(|i| &chart.notes[i]) {
+                eprintln!(
+                    "warning: {}: {} does not fit in ugc, clamped its cell and width to 35 (measure {}, offset {})",
+                    args.file,
+                    note.tag(),
+                    note.measure(),
+                    note.offset()
+                );
+            }
+
+            output(args, &ugc::to_ugc(&chart))?;
+        }
+        Some("json") => output(args, &format!("{:#}\n", chart.to_json()))?,
+        Some("c2s") => output(args, &chart.to_string())?,
+        Some(format) => {
+            eprintln!(
+                "error: unknown format {}, expected ugc, json or c2s",
+                format
+            );
+            return Ok(false);
+        }
+        None => {
+            eprintln!("error: missing --to <format>");
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn mirror(mut chart: ChuniChart, args: &Args) -> io::Result<bool> {
+    for i in transform::mirror(&mut chart) {
+        let note = &chart.notes[i];
+        eprintln!(
+            "warning: {}: {} sticks out of the playfield, moved to the left edge (measure {}, offset {})",
+            args.file,
+            note.tag(),
+            note.measure(),
+            note.offset()
+        );
+    }
+    chart.sort();
+    output(args, &chart.to_string()).map(|_| true)
+}
+
+fn random(mut chart: ChuniChart, args: &Args) -> io::Result<bool> {
+    let seed = match args.seed {
+        Some(seed) => seed,
+        None => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let seed = now.as_nanos() as u64;
+            eprintln!("seed: {}", seed);
+            seed
+        }
+    };
+
+    match args.mode.as_deref().unwrap_or("random") {
+        "random" => transform::random(&mut chart, seed),
+        "full" => transform::full_random(&mut chart, seed),
+        "s" => transform::s_random(&mut chart, seed),
+        mode => {
+            eprintln!("error: unknown mode {}, expected random, full or s", mode);
+            return Ok(false);
+        }
+    }
+
+    chart.sort();
+    output(args, &chart.to_string()).map(|_| true)
+}
+
+fn fmt(args: &Args) -> Result<bool, String> {
+    let input = fs::read_to_string(&args.file).map_err(|e| e.to_string())?;
+    let formatted = load(&args.file, |input| format::format(&input))?;
+
+    if args.check {
+        if input != formatted {
+            eprintln!("{}: not formatted", args.file);
+            return Ok(false);
+        }
+
+        return Ok(true);
+    }
+
+    output(args, &formatted)
+        .map(|_| true)
+        .map_err(|e| e.to_string())
+}
+
+fn click(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let Some(path) = &args.output else {
+        eprintln!("error: missing --output <file>");
+        return Ok(false);
+    };
+
+    fs::write(path, click::to_wav(chart, &ClickOptions::default())).map(|_| true)
+}
+
+fn autoplay(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let frames = chart.autoplay();
+
+    if args.json {
+        return output(args, &format!("{:#}\n", frames.to_json())).map(|_| true);
+    }
+
+    let mut out = String::new();
+    for frame in frames {
+        let cells = (0..16)
+            .map(|cell| if frame.is_held(cell) { '#' } else { '.' })
+            .collect::<String>();
+        out += &format!(
+            "{:>10.3} {:>10.3} {} {:?}\n",
+            frame.start, frame.end, cells, frame.air
+        );
+    }
+
+    output(args, &out).map(|_| true)
+}
+
+fn judge(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let Some(file) = &args.input else {
+        eprintln!("error: missing --input <file>");
+        return Ok(false);
+    };
+
+    let inputs = match load(file, |input| InputEvent::parse_log(&input)) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("error: {}: {}", file, e);
+            return Ok(false);
+        }
+    };
+
+    let result = chart.judge(&inputs, &TimingWindows::default());
+    if args.json {
+        return output(args, &format!("{:#}\n", result.to_json())).map(|_| true);
+    }
+
+    let mut out = String::new();
+    out += &format!("max combo:        {}\n", result.max_combo);
+    out += &format!("justice critical: {}\n", result.justice_critical);
+    out += &format!("justice:          {}\n", result.justice);
+    out += &format!("attack:           {}\n", result.attack);
+    out += &format!("miss:             {}\n", result.miss);
+    out += &format!("score:            {}\n", result.score);
+    out += &format!("rank:             {}\n", Rank::from_score(result.score));
+    out += &format!("lamp:             {}\n", result.counts().lamp());
+
+    output(args, &out).map(|_| true)
+}
+
+fn patterns(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let patterns = chart.patterns();
+
+    if args.json {
+        return output(args, &format!("{:#}\n", patterns.to_json())).map(|_| true);
+    }
+
+    let out = patterns
+        .iter()
+        .map(|pattern| {
+            format!(
+                "measure {:>3}-{:<3} {:<10} {} notes\n",
+                pattern.start_measure, pattern.end_measure, pattern.kind, pattern.notes
+            )
+        })
+        .collect::<String>();
+
+    output(args, &out).map(|_| true)
+}
+
+fn hands(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let hands = chart.hands();
+
+    if args.json {
+        let out = format!("{:#}\n", hands.to_json());
+        return output(args, &out).map(|_| hands.conflicts.is_empty());
+    }
+
+    let mut out = format!(
+        "left: {}\nright: {}\n",
+        hands.count(Hand::Left),
+        hands.count(Hand::Right)
+    );
+    for conflict in &hands.conflicts {
+        out += &format!(
+            "measure {:>3} offset {:>3}: {} hands needed for {} notes\n",
+            conflict.measure,
+            conflict.offset,
+            conflict.hands,
+            conflict.notes.len()
+        );
+    }
+
+    output(args, &out).map(|_| hands.conflicts.is_empty())
+}
+
+fn gimmicks(chart: &ChuniChart, args: &Args) -> io::Result<bool> {
+    let report = chart.gimmicks(&GimmickOptions::default());
+
+    if args.json {
+        return output(args, &format!("{:#}\n", report.to_json())).map(|_| true);
+    }
+
+    let mut out = String::new();
+    for gimmick in &report.gimmicks {
+        out += &format!(
+            "measure {:>3} offset {:>3}: {:<13} x{:<8.3} at {:.3} bpm, {} ticks\n",
+            gimmick.measure,
+            gimmick.offset,
+            gimmick.kind,
+            gimmick.multiplier,
+            gimmick.bpm,
+            gimmick.end_tick - gimmick.start_tick
+        );
+    }
+    for note in &report.fast_notes {
+        out += &format!(
+            "measure {:>3} offset {:>3}: {} on screen for {:.0}ms\n",
+            note.measure,
+            note.offset,
+            chart.notes[note.note].tag(),
+            note.approach_time
+        );
+    }
+
+    output(args, &out).map(|_| true)
+}
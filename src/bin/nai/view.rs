@@ -0,0 +1,352 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use nai_rs::{note::NoteType, scroll::ScrollMap, ChuniChart};
+
+/// The amount of rows a measure takes up on screen.
+const ROWS_PER_MEASURE: usize = 16;
+/// The delay between two frames.
+const FRAME: Duration = Duration::from_millis(33);
+/// How often the size of the terminal is checked again.
+const RESIZE_CHECK: Duration = Duration::from_secs(1);
+const PLAYBACK_RATES: [f64; 7] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+const HELP: &str = "space: pause  left/right: seek measure  g: go to measure  +/-: rate  q: quit";
+
+/// Puts the terminal in raw mode on an alternate screen, and restores it
+/// when dropped. Ctrl-C is read as a key rather than killing the viewer, so
+/// the terminal is always restored.
+struct Terminal {
+    saved: Option<String>,
+}
+
+impl Terminal {
+    fn new() -> Terminal {
+        let saved = stty(&["-g"]).map(|x| x.trim().to_string());
+        let _ = stty(&["-icanon", "-echo", "-isig", "min", "1"]);
+
+        print!("\x1b[?1049h\x1b[?25l");
+        let _ = io::stdout().flush();
+
+        Terminal { saved }
+    }
+
+    /// The size of the terminal as `(rows, columns)`.
+    fn size() -> (usize, usize) {
+        stty(&["size"])
+            .and_then(|size| {
+                let (rows, columns) = size.trim().split_once(' ')?;
+                Some((rows.parse().ok()?, columns.parse().ok()?))
+            })
+            .unwrap_or((24, 80))
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+
+        match &self.saved {
+            Some(saved) => stty(&[saved.as_str()]),
+            None => stty(&["sane"]),
+        };
+    }
+}
+
+/// Runs `stty` on the terminal attached to stdin, returning its output.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+enum Key {
+    Char(char),
+    Left,
+    Right,
+    Enter,
+    Escape,
+    Backspace,
+    Interrupt,
+}
+
+/// Reads keys from stdin on a separate thread, as reading blocks.
+fn keys() -> Receiver<Key> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        let mut buffer = [0; 1];
+        let mut escape = vec![];
+
+        while stdin.read(&mut buffer).is_ok_and(|n| n > 0) {
+            let byte = buffer[0];
+
+            if !escape.is_empty() || byte == 0x1b {
+                escape.push(byte);
+                let key = match escape.as_slice() {
+                    [0x1b] | [0x1b, b'['] => continue,
+                    [0x1b, b'[', b'C'] => Key::Right,
+                    [0x1b, b'[', b'D'] => Key::Left,
+                    [0x1b, b'[', ..] if !byte.is_ascii_alphabetic() && byte != b'~' => continue,
+                    [0x1b, b'[', ..] => {
+                        escape.clear();
+                        continue;
+                    }
+                    _ => Key::Escape,
+                };
+
+                escape.clear();
+                if sender.send(key).is_err() {
+                    return;
+                }
+                continue;
+            }
+
+            let key = match byte {
+                b'\r' | b'\n' => Key::Enter,
+                0x7f | 0x08 => Key::Backspace,
+                0x03 => Key::Interrupt,
+                byte => Key::Char(byte as char),
+            };
+
+            if sender.send(key).is_err() {
+                return;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Draws the playfield, starting at `tick` at the bottom row and looking
+/// ahead `rows` rows. Notes and measure lines move following the scroll
+/// speed changes of the chart.
+fn playfield(chart: &ChuniChart, scroll: &ScrollMap, tick: f64, rows: usize) -> Vec<String> {
+    let resolution = chart.resolution as f64;
+    let row_length = resolution / ROWS_PER_MEASURE as f64;
+    let now = scroll.position(tick);
+    let row_of = |tick: usize| (scroll.position(tick as f64) - now) / row_length;
+    let mut grid = vec![[(' ', 0u8); 16]; rows];
+
+    let mut put = |row: f64, cell: usize, width: usize, c: char, color: u8| {
+        if row < 0.0 || row >= rows as f64 {
+            return;
+        }
+
+        let row = &mut grid[row as usize];
+        for x in row.iter_mut().take((cell + width).min(16)).skip(cell) {
+            *x = (c, color);
+        }
+    };
+
+    for note in &chart.notes {
+        let start = note.tick(chart.resolution);
+        let row = row_of(start);
+
+        // bodies are drawn row by row, following the note as it moves.
+        if let Some(duration) = note.duration() {
+            let end = (note.cell(), note.width());
+            let (end_cell, end_width) = note.end().unwrap_or(end);
+            let (c, color) = match note {
+                NoteType::Hold(_) | NoteType::HoldWithExTapHead(_) => ('|', 33),
+                NoteType::AirHold(_)
+                | NoteType::AirSlide(_)
+                | NoteType::AirSlideControlPoint(_) => (':', 32),
+                NoteType::AirCrush(_) => ('.', 35),
+                _ => ('|', 34),
+            };
+
+            let length = row_of(start + duration) - row;
+            let mut step = row.max(0.0).ceil();
+            while step < (row + length).min(rows as f64) {
+                let progress = (step - row) / length;
+                let cell = note.cell() as f64 + (end_cell as f64 - note.cell() as f64) * progress;
+                let width =
+                    note.width() as f64 + (end_width as f64 - note.width() as f64) * progress;
+                put(
+                    step,
+                    cell.round() as usize,
+                    width.round().max(1.0) as usize,
+                    c,
+                    color,
+                );
+                step += 1.0;
+            }
+        }
+
+        let (c, color) = match note {
+            NoteType::Tap(_) => ('=', 31),
+            NoteType::ExTap(_)
+            | NoteType::HoldWithExTapHead(_)
+            | NoteType::SlideWithExTapHead(_)
+            | NoteType::SlideControlPointWithExTapHead(_) => ('=', 93),
+            NoteType::Flick(_) => ('~', 36),
+            NoteType::Mine(_) => ('x', 90),
+            NoteType::Hold(_) => ('=', 33),
+            NoteType::Slide(_) => ('=', 34),
+            NoteType::Air(_) | NoteType::AirUpLeft(_) | NoteType::AirUpRight(_) => ('^', 32),
+            NoteType::AirDown(_) | NoteType::AirDownLeft(_) | NoteType::AirDownRight(_) => {
+                ('v', 35)
+            }
+            NoteType::AirHold(_) | NoteType::AirSlide(_) => ('^', 32),
+            NoteType::SlideControlPoint(_)
+            | NoteType::AirSlideControlPoint(_)
+            | NoteType::AirCrush(_) => continue,
+        };
+
+        put(row, note.cell(), note.width(), c, color);
+    }
+
+    let mut measures = vec![false; rows];
+    let last_measure = chart
+        .notes
+        .iter()
+        .map(|note| note.tick(chart.resolution) + note.duration().unwrap_or(0))
+        .max()
+        .unwrap_or(0)
+        / chart.resolution;
+    for measure in 0..=last_measure + 1 {
+        let row = row_of(measure * chart.resolution);
+        if row >= 0.0 && row < rows as f64 {
+            measures[row as usize] = true;
+        }
+    }
+
+    grid.iter()
+        .enumerate()
+        .rev()
+        .map(|(i, row)| {
+            let on_measure = measures[i];
+            let mut line = String::from(if on_measure { "-|" } else { " |" });
+
+            for (c, color) in row {
+                if *color == 0 {
+                    line.push_str(if on_measure { "--" } else { "  " });
+                } else {
+                    write!(line, "\x1b[{}m{}{}\x1b[0m", color, c, c).unwrap();
+                }
+            }
+
+            line.push('|');
+            line
+        })
+        .collect()
+}
+
+/// Plays the chart back in the terminal, scrolling in real time.
+pub fn view(chart: &ChuniChart) -> io::Result<()> {
+    let scroll = ScrollMap::new(chart);
+    let tempo = scroll.tempo_map();
+    let last_tick = chart.statistics().last_tick;
+    let end = tempo.ms(last_tick);
+    let keys = keys();
+    let _terminal = Terminal::new();
+
+    let mut time = 0.0;
+    let mut paused = false;
+    let mut rate = PLAYBACK_RATES.iter().position(|x| *x == 1.0).unwrap();
+    let mut prompt: Option<String> = None;
+    let mut last_frame = Instant::now();
+    let (mut rows, _) = Terminal::size();
+    let mut last_resize_check = last_frame;
+
+    loop {
+        loop {
+            let key = match keys.try_recv() {
+                Ok(key) => key,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            };
+
+            let measure = tempo.tick(time) as usize / chart.resolution;
+            match (&mut prompt, key) {
+                (_, Key::Interrupt) => return Ok(()),
+                (Some(input), Key::Char(c)) if c.is_ascii_digit() => input.push(c),
+                (Some(input), Key::Backspace) => {
+                    input.pop();
+                }
+                (Some(input), Key::Enter) => {
+                    if let Ok(measure) = input.parse::<usize>() {
+                        time = tempo.ms(measure * chart.resolution).min(end);
+                    }
+                    prompt = None;
+                }
+                (Some(_), _) => prompt = None,
+                (None, Key::Char('q')) | (None, Key::Escape) => return Ok(()),
+                (None, Key::Char(' ')) => paused = !paused,
+                (None, Key::Char('g')) => prompt = Some(String::new()),
+                (None, Key::Char('+')) | (None, Key::Char('=')) => {
+                    rate = (rate + 1).min(PLAYBACK_RATES.len() - 1)
+                }
+                (None, Key::Char('-')) => rate = rate.saturating_sub(1),
+                (None, Key::Left) => {
+                    time = tempo.ms(measure.saturating_sub(1) * chart.resolution);
+                }
+                (None, Key::Right) => {
+                    time = tempo.ms((measure + 1) * chart.resolution).min(end);
+                }
+                (None, _) => {}
+            }
+        }
+
+        let now = Instant::now();
+        if !paused {
+            time += (now - last_frame).as_secs_f64() * 1000.0 * PLAYBACK_RATES[rate];
+            if time >= end {
+                time = end;
+                paused = true;
+            }
+        }
+        last_frame = now;
+
+        let tick = tempo.tick(time);
+        if now - last_resize_check >= RESIZE_CHECK {
+            (rows, _) = Terminal::size();
+            last_resize_check = now;
+        }
+        let mut frame = String::from("\x1b[H");
+
+        writeln!(
+            frame,
+            "measure {:>4}  bpm {:>8.3}  speed x{:<5.2}  rate x{:<4.2} {}\x1b[K\r",
+            tick as usize / chart.resolution,
+            tempo.bpm(tick as usize),
+            scroll.multiplier(tick as usize),
+            PLAYBACK_RATES[rate],
+            if paused { "[paused]" } else { "" }
+        )
+        .unwrap();
+
+        for line in playfield(chart, &scroll, tick, rows.saturating_sub(3).max(1)) {
+            writeln!(frame, "{}\x1b[K\r", line).unwrap();
+        }
+
+        match &prompt {
+            Some(input) => write!(frame, "go to measure: {}", input).unwrap(),
+            None => write!(frame, "{}", HELP).unwrap(),
+        }
+        frame.push_str("\x1b[J");
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(frame.as_bytes())?;
+        stdout.flush()?;
+
+        thread::sleep(FRAME.saturating_sub(now.elapsed()));
+    }
+}
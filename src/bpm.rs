@@ -1,11 +1,17 @@
-use std::{str::FromStr, string::ParseError};
+use std::{fmt, str::FromStr, string::ParseError};
 
-/// Default BPM designation for the chart.
-#[derive(Debug, Default, PartialEq)]
+use crate::ChuniChart;
+
+/// Default BPM designation for the chart. The starting, highest and lowest
+/// BPM follow from the [`Bpm`] designations, see
+/// [`ChuniChart::expected_bpm_def`].
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct BpmDef {
     /// The starting BPM of the chart.
     pub starting_bpm: f64,
-    /// Unknown meaning.
+    /// The BPM a song with BPM changes is listed with. This is thought to
+    /// be the BPM the chart is played at for the longest time, but that is
+    /// unconfirmed, as it is also the starting BPM in the charts checked.
     pub mode: f64,
     /// The highest BPM the chart uses.
     pub highest_bpm: f64,
@@ -35,7 +41,7 @@ impl FromStr for BpmDef {
 }
 
 /// BPM designation for a specified measure in the chart.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Bpm {
     /// The starting measure of the designation.
     pub beginning_measure: usize,
@@ -64,3 +70,68 @@ impl FromStr for Bpm {
         })
     }
 }
+
+impl fmt::Display for BpmDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BPM_DEF\t{:.3}\t{:.3}\t{:.3}\t{:.3}",
+            self.starting_bpm, self.mode, self.highest_bpm, self.lowest_bpm
+        )
+    }
+}
+
+impl fmt::Display for Bpm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BPM\t{}\t{}\t{:.3}",
+            self.beginning_measure, self.offset, self.bpm
+        )
+    }
+}
+
+impl ChuniChart {
+    /// The [`BpmDef`] that matches the BPM designations of the chart.
+    ///
+    /// `mode` is guessed as the BPM played the longest, up to the end of the
+    /// last note, which may not be how the game picks it.
+    pub fn expected_bpm_def(&self) -> BpmDef {
+        let tempo = self.tempo_map();
+        let last_tick = self.statistics().last_tick;
+
+        let mut bpms = self
+            .bpm
+            .iter()
+            .map(|x| (x.beginning_measure * self.resolution + x.offset, x.bpm))
+            .collect::<Vec<_>>();
+        bpms.sort_by_key(|(tick, _)| *tick);
+
+        // how long each BPM is played at, in milliseconds.
+        let mut lengths: Vec<(f64, f64)> = vec![];
+        for (i, (tick, bpm)) in bpms.iter().enumerate() {
+            let end = bpms.get(i + 1).map(|x| x.0).unwrap_or(last_tick).max(*tick);
+            let length = tempo.ms(end) - tempo.ms(*tick);
+            match lengths.iter_mut().find(|(x, _)| x == bpm) {
+                Some((_, total)) => *total += length,
+                None => lengths.push((*bpm, length)),
+            }
+        }
+
+        let bpm = |f: fn(f64, f64) -> f64| bpms.iter().map(|x| x.1).reduce(f).unwrap_or_default();
+        BpmDef {
+            starting_bpm: bpms.first().map(|x| x.1).unwrap_or_default(),
+            // the first BPM wins a tie.
+            mode: lengths
+                .iter()
+                .fold(None, |mode: Option<(f64, f64)>, x| match mode {
+                    Some(mode) if mode.1 >= x.1 => Some(mode),
+                    _ => Some(*x),
+                })
+                .map(|x| x.0)
+                .unwrap_or_default(),
+            highest_bpm: bpm(f64::max),
+            lowest_bpm: bpm(f64::min),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    f64::consts::TAU,
+    io::{self, Write},
+};
+
+use crate::{note::NoteType, ChuniChart};
+
+/// Settings used when generating a click track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClickOptions {
+    /// Samples per second of the generated audio.
+    pub sample_rate: u32,
+    /// Whether or not to click on every beat.
+    pub metronome: bool,
+    /// Whether or not to play a hitsound on every note.
+    pub hitsounds: bool,
+    /// Loudness of a single sound, from 0 to 1.
+    pub volume: f64,
+}
+
+impl Default for ClickOptions {
+    fn default() -> Self {
+        ClickOptions {
+            sample_rate: 44100,
+            metronome: true,
+            hitsounds: true,
+            volume: 0.5,
+        }
+    }
+}
+
+/// The time left after the end of the last note, in milliseconds.
+const TAIL: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sound {
+    Downbeat,
+    Beat,
+    Tap,
+    ExTap,
+    Flick,
+    Air,
+}
+
+impl Sound {
+    /// The length of the sound, in seconds.
+    fn length(&self) -> f64 {
+        match self {
+            Sound::Downbeat | Sound::Beat => 0.05,
+            Sound::Tap => 0.03,
+            Sound::ExTap | Sound::Flick => 0.06,
+            Sound::Air => 0.08,
+        }
+    }
+
+    /// Synthesizes the sound at the given sample rate.
+    fn synthesize(&self, sample_rate: u32) -> Vec<f64> {
+        let mut noise = Noise(0x2545_f491);
+        let samples = (self.length() * sample_rate as f64) as usize;
+
+        (0..samples)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let sine = |frequency: f64| (TAU * frequency * t).sin();
+
+                match self {
+                    Sound::Downbeat => sine(1760.0) * (-t / 0.012).exp(),
+                    Sound::Beat => sine(880.0) * (-t / 0.012).exp() * 0.8,
+                    Sound::Tap => sine(2400.0) * (-t / 0.006).exp(),
+                    Sound::ExTap => {
+                        (sine(2400.0) * 0.6 + sine(3600.0) * 0.4) * (-t / 0.015).exp()
+                            + noise.next() * (-t / 0.004).exp() * 0.3
+                    }
+                    Sound::Flick => noise.next() * (-t / 0.015).exp() * 0.7,
+                    Sound::Air => {
+                        // a rising sweep, from 600Hz to 1200Hz over the length
+                        // of the sound.
+                        let phase = TAU * (600.0 * t + 600.0 / self.length() * t * t / 2.0);
+                        phase.sin() * (-t / 0.03).exp()
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// A small xorshift generator, so the noise is the same on every run.
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f64 / u32::MAX as f64 * 2.0 - 1.0
+    }
+}
+
+impl NoteType {
+    /// The hitsound played for the note, if any. Slide segments sound like
+    /// the head of a chain, so the ones continuing a chain have to be left
+    /// out by the caller. AirSlide control points, Mines and AirCrushes are
+    /// not hit, so they have none.
+    fn hitsound(&self) -> Option<Sound> {
+        let sound = match self {
+            NoteType::Tap(_)
+            | NoteType::Hold(_)
+            | NoteType::Slide(_)
+            | NoteType::SlideControlPoint(_) => Sound::Tap,
+            NoteType::ExTap(_)
+            | NoteType::HoldWithExTapHead(_)
+            | NoteType::SlideWithExTapHead(_)
+            | NoteType::SlideControlPointWithExTapHead(_) => Sound::ExTap,
+            NoteType::Flick(_) => Sound::Flick,
+            NoteType::Air(_)
+            | NoteType::AirUpLeft(_)
+            | NoteType::AirUpRight(_)
+            | NoteType::AirDown(_)
+            | NoteType::AirDownLeft(_)
+            | NoteType::AirDownRight(_)
+            | NoteType::AirHold(_)
+            | NoteType::AirSlide(_) => Sound::Air,
+            NoteType::AirSlideControlPoint(_) | NoteType::AirCrush(_) | NoteType::Mine(_) => {
+                return None
+            }
+        };
+
+        Some(sound)
+    }
+}
+
+/// Returns the position of every beat of the chart, and whether or not it is
+/// the first beat of a bar.
+///
+/// A beat is a `1/denominator` note of the time signature in effect, and a
+/// bar is `numerator` beats long.
+fn beats(chart: &ChuniChart, end: usize) -> Vec<(usize, bool)> {
+    let mets = chart.time_signatures();
+
+    let mut beats = vec![];
+    for (i, (start, signature)) in mets.iter().enumerate() {
+        let until = mets.get(i + 1).map(|x| x.0).unwrap_or(end + 1);
+        let length = signature.beat_length(chart.resolution).max(1);
+
+        for (beat, tick) in (*start..until).step_by(length).enumerate() {
+            beats.push((tick, beat % signature.numerator.max(1) == 0));
+        }
+    }
+
+    beats
+}
+
+/// Generates a mono click track of the chart: a metronome click on every
+/// beat, accented on the first beat of every bar, and a hitsound at the time
+/// of every note.
+///
+/// Taps, ExTaps, Flicks and air notes each have their own hitsound. Holds and
+/// Slides use the sound of their head. The track starts at the start of the
+/// chart, so it lines up with the audio of the song.
+pub fn click_track(chart: &ChuniChart, options: &ClickOptions) -> Vec<i16> {
+    let tempo = chart.tempo_map();
+    let last_tick = chart.statistics().last_tick;
+    let rate = options.sample_rate as f64;
+    let length = ((tempo.ms(last_tick) + TAIL) / 1000.0 * rate) as usize;
+    let mut mix = vec![0.0; length];
+
+    let mut events = vec![];
+    if options.metronome {
+        for (tick, downbeat) in beats(chart, last_tick) {
+            let sound = if downbeat {
+                Sound::Downbeat
+            } else {
+                Sound::Beat
+            };
+            events.push((tempo.ms(tick), sound));
+        }
+    }
+    if options.hitsounds {
+        // only the first segment of a slide chain is hit.
+        let slide_ends = chart.slide_ends();
+        let notes = chart.notes.iter().filter(|note| {
+            note.slide().is_none()
+                || !slide_ends.contains(&(note.tick(chart.resolution), note.cell(), note.width()))
+        });
+
+        for note in notes {
+            if let Some(sound) = note.hitsound() {
+                events.push((tempo.ms(note.tick(chart.resolution)), sound));
+            }
+        }
+    }
+
+    let sounds = [
+        Sound::Downbeat,
+        Sound::Beat,
+        Sound::Tap,
+        Sound::ExTap,
+        Sound::Flick,
+        Sound::Air,
+    ]
+    .map(|sound| (sound, sound.synthesize(options.sample_rate)));
+
+    for (ms, sound) in events {
+        let start = (ms / 1000.0 * rate).round() as usize;
+        let (_, samples) = sounds.iter().find(|(x, _)| *x == sound).unwrap();
+
+        for (out, sample) in mix.iter_mut().skip(start).zip(samples) {
+            *out += sample;
+        }
+    }
+
+    mix.iter()
+        .map(|x| (x * options.volume).clamp(-1.0, 1.0))
+        .map(|x| (x * i16::MAX as f64) as i16)
+        .collect()
+}
+
+/// Writes mono 16-bit PCM samples as a WAV file.
+pub fn write_wav(mut writer: impl Write, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    let data = samples.len() as u32 * 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // mono
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * 2).to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Generates the click track of the chart with [`click_track`], as a WAV
+/// file.
+pub fn to_wav(chart: &ChuniChart, options: &ClickOptions) -> Vec<u8> {
+    let samples = click_track(chart, options);
+    let mut wav = Vec::with_capacity(44 + samples.len() * 2);
+    write_wav(&mut wav, options.sample_rate, &samples).unwrap();
+    wav
+}
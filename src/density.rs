@@ -0,0 +1,342 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+
+use crate::{
+    json::{Json, ToJson},
+    note::NoteType,
+    ChuniChart,
+};
+
+/// How many times Holds, Slides, AirHolds and AirSlides are judged per
+/// measure while they are held.
+const JUDGEMENTS_PER_MEASURE: usize = 8;
+
+/// Note density of a single measure.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MeasureDensity {
+    pub measure: usize,
+    /// Amount of notes in the measure.
+    pub notes: usize,
+    /// Notes per second over the length of the measure.
+    pub nps: f64,
+}
+
+/// Notes per second of a chart over time.
+///
+/// Only notes that are hit on their own are counted: control points of
+/// Slides and AirSlides only shape the note they belong to, and Mines are
+/// not meant to be hit at all.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Density {
+    /// The length of the window used for `peak_nps` and `timeline`, in
+    /// milliseconds.
+    pub window: f64,
+    /// Notes per second between the first and the last note.
+    pub average_nps: f64,
+    /// The highest notes per second in any window of the chart.
+    pub peak_nps: f64,
+    /// The start of the window with the highest notes per second, in
+    /// milliseconds.
+    pub peak_ms: f64,
+    /// Notes per second in consecutive windows, starting at the start of the
+    /// chart.
+    pub timeline: Vec<f64>,
+    /// Note density of every measure, up to the last note.
+    pub measures: Vec<MeasureDensity>,
+}
+
+impl NoteType {
+    /// Whether or not the note counts towards the note density of a chart.
+    pub(crate) fn is_counted(&self) -> bool {
+        !matches!(
+            self,
+            NoteType::SlideControlPoint(_)
+                | NoteType::SlideControlPointWithExTapHead(_)
+                | NoteType::AirSlideControlPoint(_)
+                | NoteType::Mine(_)
+        )
+    }
+}
+
+impl ChuniChart {
+    /// Returns the time of every note counted by [`Density`], in
+    /// milliseconds, sorted.
+    fn note_times(&self) -> Vec<f64> {
+        let tempo = self.tempo_map();
+        let mut times = self
+            .notes
+            .iter()
+            .filter(|note| note.is_counted())
+            .map(|note| tempo.ms(note.tick(self.resolution)))
+            .collect::<Vec<_>>();
+        times.sort_by(f64::total_cmp);
+        times
+    }
+
+    /// Returns every chain of the notes matching `filter`, as the indices of
+    /// its notes in order, following notes that start where another one
+    /// ends, at the same cells. Where several notes start at the same place,
+    /// the chain follows the first one.
+    fn chains(&self, filter: impl Fn(&NoteType) -> bool) -> Vec<Vec<usize>> {
+        let segments = self
+            .notes
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| filter(note))
+            .filter_map(|(i, note)| {
+                let start = (note.tick(self.resolution), note.cell(), note.width());
+                Some((i, start, note.duration()?, note.end()?))
+            })
+            .collect::<Vec<_>>();
+        let ends = segments
+            .iter()
+            .map(|(_, (tick, _, _), duration, (cell, width))| (tick + duration, *cell, *width))
+            .collect::<HashSet<_>>();
+        let mut next = HashMap::new();
+        for (i, start, _, _) in &segments {
+            next.entry(*start).or_insert(*i);
+        }
+
+        segments
+            .iter()
+            .filter(|(_, start, _, _)| !ends.contains(start))
+            .map(|(i, start, duration, end)| {
+                let mut chain = vec![*i];
+                let (mut tick, mut end) = (start.0 + duration, *end);
+                while let Some(&i) = next.get(&(tick, end.0, end.1)) {
+                    let note = &self.notes[i];
+                    let duration = note.duration().unwrap_or_default();
+                    if duration == 0 {
+                        break;
+                    }
+                    chain.push(i);
+                    tick += duration;
+                    end = note.end().unwrap_or(end);
+                }
+                chain
+            })
+            .collect()
+    }
+
+    /// Returns the position of every judgement of every note, in ticks, in
+    /// the order of `notes`. See [`ChuniChart::judgement_ticks`].
+    ///
+    /// The judgements of a chain go to the segment they fall in, and the
+    /// ones on a joint to the segment ending there.
+    pub(crate) fn note_judgements(&self) -> Vec<Vec<usize>> {
+        let interval = (self.resolution / JUDGEMENTS_PER_MEASURE).max(1);
+        let body =
+            |head: usize, length: usize| (1..=length / interval).map(move |i| head + i * interval);
+
+        let mut judgements = self
+            .notes
+            .iter()
+            .map(|note| {
+                let tick = note.tick(self.resolution);
+                match note {
+                    NoteType::Tap(_)
+                    | NoteType::ExTap(_)
+                    | NoteType::Flick(_)
+                    | NoteType::Air(_)
+                    | NoteType::AirUpLeft(_)
+                    | NoteType::AirUpRight(_)
+                    | NoteType::AirDown(_)
+                    | NoteType::AirDownLeft(_)
+                    | NoteType::AirDownRight(_) => vec![tick],
+                    NoteType::Hold(hold) | NoteType::HoldWithExTapHead(hold) => {
+                        iter::once(tick).chain(body(tick, hold.duration)).collect()
+                    }
+                    NoteType::AirHold(hold) => body(tick, hold.duration).collect(),
+                    _ => vec![],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let slides = self.chains(|note| note.slide().is_some());
+        let air_slides = self.chains(|note| {
+            matches!(
+                note,
+                NoteType::AirSlide(_) | NoteType::AirSlideControlPoint(_)
+            )
+        });
+        let end = |i: usize| {
+            let note = &self.notes[i];
+            note.tick(self.resolution) + note.duration().unwrap_or_default()
+        };
+
+        for (chain, has_head) in slides
+            .iter()
+            .map(|x| (x, true))
+            .chain(air_slides.iter().map(|x| (x, false)))
+        {
+            let (first, last) = (chain[0], chain[chain.len() - 1]);
+            let head = self.notes[first].tick(self.resolution);
+            if has_head {
+                judgements[first].push(head);
+            }
+
+            for tick in body(head, end(last) - head) {
+                let i = chain.iter().find(|i| end(**i) >= tick).unwrap_or(&last);
+                judgements[*i].push(tick);
+            }
+        }
+
+        judgements
+    }
+
+    /// Returns the position of every judgement of the chart, in ticks,
+    /// sorted. The max combo of the chart is the amount of judgements.
+    ///
+    /// Taps, ExTaps, Flicks, air notes and the heads of Holds and Slides are
+    /// judged once. Holds, Slides, AirHolds and AirSlides are also judged
+    /// every eighth of a measure after their head, up to their end. Chains
+    /// of Slides and AirSlides are judged as a whole, from the head of the
+    /// chain.
+    ///
+    /// This approximates how the game counts judgements, which is not fully
+    /// known: for `tests/zegallta_master.c2s` it gives 2884 judgements, while
+    /// the footer's `T_JUDGE_ALL` says 2913.
+    pub fn judgement_ticks(&self) -> Vec<usize> {
+        let mut ticks = self.note_judgements().concat();
+        ticks.sort();
+        ticks
+    }
+
+    /// Computes the notes per second of the chart, using windows of `window`
+    /// milliseconds for the peak and the timeline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is not a positive amount of milliseconds.
+    pub fn density(&self, window: f64) -> Density {
+        assert!(
+            window > 0.0,
+            "density window must be positive, got {}ms",
+            window
+        );
+
+        let tempo = self.tempo_map();
+        let times = self.note_times();
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return Density {
+                window,
+                ..Default::default()
+            };
+        };
+
+        let average_nps = if last > first {
+            times.len() as f64 / ((last - first) / 1000.0)
+        } else {
+            0.0
+        };
+
+        let mut peak = 0;
+        let mut peak_ms = 0.0;
+        let mut end = 0;
+        for (start, time) in times.iter().enumerate() {
+            while end < times.len() && times[end] < time + window {
+                end += 1;
+            }
+
+            if end - start > peak {
+                peak = end - start;
+                peak_ms = *time;
+            }
+        }
+
+        let mut timeline = vec![0.0; (last / window) as usize + 1];
+        for time in &times {
+            timeline[(time / window) as usize] += 1000.0 / window;
+        }
+
+        let last_measure = self
+            .notes
+            .iter()
+            .filter(|note| note.is_counted())
+            .map(|note| note.measure())
+            .max()
+            .unwrap_or_default();
+        let mut measures = (0..=last_measure)
+            .map(|measure| MeasureDensity {
+                measure,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        for note in self.notes.iter().filter(|note| note.is_counted()) {
+            measures[note.measure()].notes += 1;
+        }
+
+        for measure in &mut measures {
+            let start = tempo.ms(measure.measure * self.resolution);
+            let end = tempo.ms((measure.measure + 1) * self.resolution);
+            measure.nps = measure.notes as f64 / ((end - start) / 1000.0);
+        }
+
+        Density {
+            window,
+            average_nps,
+            peak_nps: peak as f64 / (window / 1000.0),
+            peak_ms,
+            timeline,
+            measures,
+        }
+    }
+
+    /// Splits the chart into 20 parts of equal length, from the first note
+    /// to the end of the last one, and counts the judgements in each, like
+    /// the `T_PROG_*` lines in the footer of `.c2s` files.
+    ///
+    /// Judgements are the ones of [`ChuniChart::judgement_ticks`], and
+    /// every AirSlide control point counts once more, as it does in the
+    /// footer. The counts are as approximate as the judgements are.
+    pub fn progress(&self) -> [usize; 20] {
+        let tempo = self.tempo_map();
+        let statistics = self.statistics();
+        let start = tempo.ms(statistics.first_tick);
+        let length = tempo.ms(statistics.last_tick) - start;
+
+        let control_points = self
+            .notes
+            .iter()
+            .filter(|note| matches!(note, NoteType::AirSlideControlPoint(_)))
+            .map(|note| note.tick(self.resolution));
+
+        let mut progress = [0; 20];
+        for tick in self.judgement_ticks().into_iter().chain(control_points) {
+            let bucket = if length > 0.0 {
+                ((tempo.ms(tick) - start) / length * 20.0) as usize
+            } else {
+                0
+            };
+            progress[bucket.min(19)] += 1;
+        }
+
+        progress
+    }
+}
+
+impl ToJson for MeasureDensity {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("measure", self.measure.into()),
+            ("notes", self.notes.into()),
+            ("nps", self.nps.into()),
+        ])
+    }
+}
+
+impl ToJson for Density {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("window", self.window.into()),
+            ("average_nps", self.average_nps.into()),
+            ("peak_nps", self.peak_nps.into()),
+            ("peak_ms", self.peak_ms.into()),
+            ("timeline", self.timeline.clone().into()),
+            ("measures", self.measures.to_json()),
+        ])
+    }
+}
@@ -0,0 +1,274 @@
+use std::{fmt, str::FromStr, string::ParseError};
+
+use crate::{
+    json::{Json, ToJson},
+    ChuniChart,
+};
+
+/// The difficulty a chart is played as, from the `DIFFICULT` header of a
+/// chart or the `<type>` of a difficulty in `Music.xml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Basic,
+    Advanced,
+    Expert,
+    Master,
+    Ultima,
+    WorldsEnd,
+}
+
+impl Difficulty {
+    /// The difficulty with the numerical ID used by both `DIFFICULT` and
+    /// `Music.xml`, from 0 for BASIC to 5 for WORLD'S END.
+    pub fn from_id(id: usize) -> Option<Difficulty> {
+        match id {
+            0 => Some(Difficulty::Basic),
+            1 => Some(Difficulty::Advanced),
+            2 => Some(Difficulty::Expert),
+            3 => Some(Difficulty::Master),
+            4 => Some(Difficulty::Ultima),
+            5 => Some(Difficulty::WorldsEnd),
+            _ => None,
+        }
+    }
+
+    /// The numerical ID of the difficulty, the reverse of
+    /// [`Difficulty::from_id`].
+    pub fn id(&self) -> usize {
+        *self as usize
+    }
+
+    /// Whether or not the creator of the chart is shown on the song card.
+    /// It is hidden for BASIC and ADVANCED.
+    pub fn shows_creator(&self) -> bool {
+        *self >= Difficulty::Expert
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ParseError;
+
+    /// Parses the name of a difficulty as written in `Music.xml`, such as
+    /// `MASTER`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let difficulty = match s.trim() {
+            "BASIC" => Difficulty::Basic,
+            "ADVANCED" => Difficulty::Advanced,
+            "EXPERT" => Difficulty::Expert,
+            "MASTER" => Difficulty::Master,
+            "ULTIMA" => Difficulty::Ultima,
+            "WORLD'S END" => Difficulty::WorldsEnd,
+            _ => unreachable!("invalid difficulty found. bailing."),
+        };
+
+        Ok(difficulty)
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Basic => "BASIC",
+            Difficulty::Advanced => "ADVANCED",
+            Difficulty::Expert => "EXPERT",
+            Difficulty::Master => "MASTER",
+            Difficulty::Ultima => "ULTIMA",
+            Difficulty::WorldsEnd => "WORLD'S END",
+        })
+    }
+}
+
+impl ChuniChart {
+    /// The [`Difficulty`] of the `difficult` field. This is usually BASIC in
+    /// the chart itself, until it is filled in from `Music.xml` with
+    /// [`MusicInfo::attach`][crate::music_xml::MusicInfo::attach].
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        Difficulty::from_id(self.difficult)
+    }
+
+    /// The creator as shown on the song card, which is only done for EXPERT
+    /// and above.
+    pub fn displayed_creator(&self) -> Option<&str> {
+        self.difficulty()
+            .filter(Difficulty::shows_creator)
+            .map(|_| self.creator.as_str())
+    }
+}
+
+/// Measurements of a chart that make it harder to play, used to estimate its
+/// level with a [`LevelEstimator`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Features {
+    /// The time from the first note to the end of the last one, in seconds.
+    pub length: f64,
+    /// Notes per second between the first and the last note.
+    pub average_nps: f64,
+    /// The highest notes per second in any second of the chart.
+    pub peak_nps: f64,
+    /// Total length of all Slide segments in milliseconds, like the
+    /// `T_LEN_SLD` line in the footer of `.c2s` files.
+    pub slide_length: f64,
+    /// The share of notes that are played in the air, from 0 to 1.
+    pub air_ratio: f64,
+    /// Amount of Flicks.
+    pub flicks: usize,
+    /// The average distance between the centers of consecutive notes, in
+    /// cells.
+    pub hand_travel: f64,
+    /// The share of ground notes that are hit at the same time as another
+    /// ground note, from 0 to 1.
+    pub simultaneous_rate: f64,
+}
+
+impl ChuniChart {
+    /// Measures the [`Features`] of the chart.
+    pub fn features(&self) -> Features {
+        let tempo = self.tempo_map();
+        let stats = self.statistics();
+        let density = self.density(1000.0);
+
+        let slide_length = self
+            .notes
+            .iter()
+            .filter_map(|note| Some((note.tick(self.resolution), note.slide()?.duration)))
+            .map(|(tick, duration)| tempo.ms(tick + duration) - tempo.ms(tick))
+            .sum();
+
+        let total = stats.total();
+        let air_ratio = match total {
+            0 => 0.0,
+            total => (stats.airs + stats.air_holds) as f64 / total as f64,
+        };
+
+        // ground notes that are hit, grouped by the position they are hit at.
+        let mut ground = self
+            .notes
+            .iter()
+            .filter(|note| note.is_ground() && note.is_counted())
+            .map(|note| {
+                let center = note.cell() as f64 + note.width() as f64 / 2.0;
+                (note.tick(self.resolution), center)
+            })
+            .collect::<Vec<_>>();
+        ground.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+        let mut groups: Vec<(usize, Vec<f64>)> = vec![];
+        for (tick, center) in &ground {
+            match groups.last_mut() {
+                Some((last, centers)) if last == tick => centers.push(*center),
+                _ => groups.push((*tick, vec![*center])),
+            }
+        }
+
+        let simultaneous = groups
+            .iter()
+            .filter(|(_, centers)| centers.len() > 1)
+            .map(|(_, centers)| centers.len())
+            .sum::<usize>();
+        let simultaneous_rate = match ground.len() {
+            0 => 0.0,
+            notes => simultaneous as f64 / notes as f64,
+        };
+
+        let centers = groups
+            .iter()
+            .map(|(_, centers)| centers.iter().sum::<f64>() / centers.len() as f64)
+            .collect::<Vec<_>>();
+        let hand_travel = match centers.len() {
+            0 | 1 => 0.0,
+            groups => {
+                centers.windows(2).map(|x| (x[1] - x[0]).abs()).sum::<f64>() / (groups - 1) as f64
+            }
+        };
+
+        Features {
+            length: (tempo.ms(stats.last_tick) - tempo.ms(stats.first_tick)) / 1000.0,
+            average_nps: density.average_nps,
+            peak_nps: density.peak_nps,
+            slide_length,
+            air_ratio,
+            flicks: stats.flicks,
+            hand_travel,
+            simultaneous_rate,
+        }
+    }
+
+    /// Estimates the internal level of the chart with the default
+    /// [`LevelEstimator`].
+    pub fn estimate_level(&self) -> f64 {
+        LevelEstimator::default().estimate(&self.features())
+    }
+}
+
+/// A linear model that predicts the internal level of a chart from its
+/// [`Features`].
+///
+/// The estimate is `intercept` plus every feature multiplied by its weight.
+/// Counts and lengths are taken per minute of the chart, so long and short
+/// charts can be compared. The default weights are only a rough starting
+/// point, and should be tuned against a set of charts with known levels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelEstimator {
+    pub intercept: f64,
+    pub average_nps: f64,
+    pub peak_nps: f64,
+    /// Weight of the Slide length, in seconds of Slides per minute.
+    pub slide_length: f64,
+    pub air_ratio: f64,
+    /// Weight of the Flicks, per minute.
+    pub flicks: f64,
+    pub hand_travel: f64,
+    pub simultaneous_rate: f64,
+    /// The lowest and highest level that can be estimated.
+    pub range: (f64, f64),
+}
+
+impl Default for LevelEstimator {
+    fn default() -> Self {
+        LevelEstimator {
+            intercept: 2.4,
+            average_nps: 0.45,
+            peak_nps: 0.05,
+            slide_length: 0.03,
+            air_ratio: 1.0,
+            flicks: 0.005,
+            hand_travel: 0.5,
+            simultaneous_rate: 1.5,
+            range: (1.0, 15.9),
+        }
+    }
+}
+
+impl LevelEstimator {
+    /// Predicts the internal level of a chart, rounded to one decimal like
+    /// the levels in `Music.xml`.
+    pub fn estimate(&self, features: &Features) -> f64 {
+        let minutes = (features.length / 60.0).max(1.0 / 60.0);
+
+        let level = self.intercept
+            + self.average_nps * features.average_nps
+            + self.peak_nps * features.peak_nps
+            + self.slide_length * features.slide_length / 1000.0 / minutes
+            + self.air_ratio * features.air_ratio
+            + self.flicks * features.flicks as f64 / minutes
+            + self.hand_travel * features.hand_travel
+            + self.simultaneous_rate * features.simultaneous_rate;
+
+        (level.clamp(self.range.0, self.range.1) * 10.0).round() / 10.0
+    }
+}
+
+impl ToJson for Features {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("length", self.length.into()),
+            ("average_nps", self.average_nps.into()),
+            ("peak_nps", self.peak_nps.into()),
+            ("slide_length", self.slide_length.into()),
+            ("air_ratio", self.air_ratio.into()),
+            ("flicks", self.flicks.into()),
+            ("hand_travel", self.hand_travel.into()),
+            ("simultaneous_rate", self.simultaneous_rate.into()),
+        ])
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    fmt,
+    io::{self, BufRead},
+    str::FromStr,
+    string::ParseError,
+};
+
+use crate::{
+    bpm::{Bpm, BpmDef},
+    met::{Met, MetDef},
+    note::NoteType,
+    sfl::Sfl,
+    version::ChartVersion,
+    ParserContext,
+};
+
+/// A line of the header of a `.c2s` file. See [`ChuniChart`][crate::ChuniChart]
+/// for the meaning of each one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Header<S = String> {
+    Version(ChartVersion),
+    Music(usize),
+    SequenceId(usize),
+    Difficult(usize),
+    Level(f64),
+    Creator(S),
+    BpmDef(BpmDef),
+    MetDef(MetDef),
+    Resolution(usize),
+    ClkDef(usize),
+    ProgJudgeBpm(f64),
+    ProgJudgeAer(f64),
+    Tutorial(bool),
+}
+
+impl<S> Header<S> {
+    /// The tag the header line starts with, e.g. `BPM_DEF`.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Header::Version(_) => "VERSION",
+            Header::Music(_) => "MUSIC",
+            Header::SequenceId(_) => "SEQUENCEID",
+            Header::Difficult(_) => "DIFFICULT",
+            Header::Level(_) => "LEVEL",
+            Header::Creator(_) => "CREATOR",
+            Header::BpmDef(_) => "BPM_DEF",
+            Header::MetDef(_) => "MET_DEF",
+            Header::Resolution(_) => "RESOLUTION",
+            Header::ClkDef(_) => "CLK_DEF",
+            Header::ProgJudgeBpm(_) => "PROGJUDGE_BPM",
+            Header::ProgJudgeAer(_) => "PROGJUDGE_AER",
+            Header::Tutorial(_) => "TUTORIAL",
+        }
+    }
+}
+
+/// A `T_*` line from the footer of a `.c2s` file, such as `T_REC_TAP 910`.
+/// These are counts and lengths written by the official editor, and are not
+/// needed to play the chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistic<S = String> {
+    /// The name of the statistic, including the `T_` prefix.
+    pub name: S,
+    /// Everything after the name, as written in the file.
+    pub value: S,
+}
+
+impl<S: AsRef<str>> Statistic<S> {
+    /// Parses the value of the statistic, usually into a number.
+    pub fn parse<T: FromStr>(&self) -> Option<T> {
+        self.value.as_ref().parse().ok()
+    }
+}
+
+/// A single line of a `.c2s` file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum C2sEvent<S = String> {
+    Header(Header<S>),
+    Bpm(Bpm),
+    Met(Met),
+    Sfl(Sfl),
+    Note(NoteType<S>),
+    Statistic(Statistic<S>),
+    /// Any other line, as written in the file.
+    Unknown(S),
+}
+
+impl FromStr for C2sEvent {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        C2sEvent::parse(line).map(C2sEvent::into_owned)
+    }
+}
+
+impl<'a> C2sEvent<&'a str> {
+    /// Parses a line like [`C2sEvent::from_str`], borrowing any text from
+    /// `line` instead of copying it.
+    pub fn parse(line: &'a str) -> Result<C2sEvent<&'a str>, ParseError> {
+        C2sEvent::parse_versioned(line, ChartVersion::default())
+    }
+
+    /// Parses a line from a chart of the given `version`, which changes how
+    /// some notes are read. See [`NoteType::parse_versioned`].
+    pub fn parse_versioned(
+        line: &'a str,
+        version: ChartVersion,
+    ) -> Result<C2sEvent<&'a str>, ParseError> {
+        let value = || line.split('\t').nth(1).unwrap().trim();
+
+        let event = match ParserContext::get_section(line) {
+            ParserContext::Version => C2sEvent::Header(Header::Version(line.parse().unwrap())),
+            ParserContext::Music => C2sEvent::Header(Header::Music(value().parse().unwrap())),
+            ParserContext::SequenceId => {
+                C2sEvent::Header(Header::SequenceId(value().parse().unwrap()))
+            }
+            ParserContext::Difficult => {
+                C2sEvent::Header(Header::Difficult(value().parse().unwrap()))
+            }
+            ParserContext::Level => C2sEvent::Header(Header::Level(value().parse().unwrap())),
+            ParserContext::Creator => {
+                let (_, creator) = line.split_once('\t').unwrap_or((line, ""));
+                C2sEvent::Header(Header::Creator(creator.trim()))
+            }
+            ParserContext::BpmDef => C2sEvent::Header(Header::BpmDef(line.parse().unwrap())),
+            ParserContext::MetDef => C2sEvent::Header(Header::MetDef(line.parse().unwrap())),
+            ParserContext::Resolution => {
+                C2sEvent::Header(Header::Resolution(value().parse().unwrap()))
+            }
+            ParserContext::ClkDef => C2sEvent::Header(Header::ClkDef(value().parse().unwrap())),
+            ParserContext::ProgJudgeBpm => {
+                C2sEvent::Header(Header::ProgJudgeBpm(value().parse().unwrap()))
+            }
+            ParserContext::ProgJudgeAer => {
+                C2sEvent::Header(Header::ProgJudgeAer(value().parse().unwrap()))
+            }
+            ParserContext::Tutorial => {
+                let tutorial = value().parse::<usize>().unwrap();
+                C2sEvent::Header(Header::Tutorial(tutorial != 0))
+            }
+            ParserContext::Bpm => C2sEvent::Bpm(line.parse().unwrap()),
+            ParserContext::Met => C2sEvent::Met(line.parse().unwrap()),
+            ParserContext::Sfl => C2sEvent::Sfl(line.parse().unwrap()),
+            ParserContext::Note => {
+                C2sEvent::Note(NoteType::parse_versioned(line, version).unwrap())
+            }
+            ParserContext::None if line.starts_with("T_") => {
+                let (name, value) = line.split_once('\t').unwrap_or((line, ""));
+                C2sEvent::Statistic(Statistic {
+                    name,
+                    value: value.trim(),
+                })
+            }
+            ParserContext::None => C2sEvent::Unknown(line),
+        };
+
+        Ok(event)
+    }
+}
+
+impl C2sEvent<&str> {
+    /// Copies the borrowed text of the event into an owned [`C2sEvent`].
+    pub fn into_owned(self) -> C2sEvent {
+        match self {
+            C2sEvent::Header(header) => C2sEvent::Header(match header {
+                Header::Version(version) => Header::Version(version),
+                Header::Music(music) => Header::Music(music),
+                Header::SequenceId(sequence_id) => Header::SequenceId(sequence_id),
+                Header::Difficult(difficult) => Header::Difficult(difficult),
+                Header::Level(level) => Header::Level(level),
+                Header::Creator(creator) => Header::Creator(creator.to_string()),
+                Header::BpmDef(bpm_def) => Header::BpmDef(bpm_def),
+                Header::MetDef(met_def) => Header::MetDef(met_def),
+                Header::Resolution(resolution) => Header::Resolution(resolution),
+                Header::ClkDef(clk_def) => Header::ClkDef(clk_def),
+                Header::ProgJudgeBpm(progjudge_bpm) => Header::ProgJudgeBpm(progjudge_bpm),
+                Header::ProgJudgeAer(progjudge_aer) => Header::ProgJudgeAer(progjudge_aer),
+                Header::Tutorial(tutorial) => Header::Tutorial(tutorial),
+            }),
+            C2sEvent::Bpm(bpm) => C2sEvent::Bpm(bpm),
+            C2sEvent::Met(met) => C2sEvent::Met(met),
+            C2sEvent::Sfl(sfl) => C2sEvent::Sfl(sfl),
+            C2sEvent::Note(note) => C2sEvent::Note(note.into_owned()),
+            C2sEvent::Statistic(statistic) => C2sEvent::Statistic(Statistic {
+                name: statistic.name.to_string(),
+                value: statistic.value.to_string(),
+            }),
+            C2sEvent::Unknown(line) => C2sEvent::Unknown(line.to_string()),
+        }
+    }
+}
+
+/// A line of a `.c2s` file that is not part of the chart itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtraLine<S = String> {
+    Statistic(Statistic<S>),
+    /// Any other line, such as a tag from a newer version of the game, as
+    /// written in the file.
+    Unknown(S),
+}
+
+impl<S: fmt::Display> fmt::Display for ExtraLine<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtraLine::Statistic(statistic) => write!(f, "{}\t{}", statistic.name, statistic.value),
+            ExtraLine::Unknown(line) => write!(f, "{}", line),
+        }
+    }
+}
+
+/// An [`ExtraLine`] kept in [`ChuniChart::extra`][crate::ChuniChart::extra],
+/// so writing the chart back out does not lose it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extra<S = String> {
+    /// The number of BPM, MET, SFL and note lines before this one in the
+    /// file, or of header lines if `header` is set. The line is written back
+    /// after the same number of lines, even if the chart has been changed in
+    /// the meantime. As those are written grouped by kind, a file that mixes
+    /// them up only keeps the count, not the neighbouring lines.
+    pub position: usize,
+    /// Whether or not the line was read among the header lines, before any
+    /// BPM, MET, SFL or note line.
+    pub header: bool,
+    pub line: ExtraLine<S>,
+}
+
+impl Extra<&str> {
+    /// Copies the borrowed text of the line into an owned [`Extra`].
+    pub fn into_owned(self) -> Extra {
+        let line = match self.line {
+            ExtraLine::Statistic(statistic) => ExtraLine::Statistic(Statistic {
+                name: statistic.name.to_string(),
+                value: statistic.value.to_string(),
+            }),
+            ExtraLine::Unknown(line) => ExtraLine::Unknown(line.to_string()),
+        };
+
+        Extra {
+            position: self.position,
+            header: self.header,
+            line,
+        }
+    }
+}
+
+/// Reads a `.c2s` file one line at a time, yielding a [`C2sEvent`] for every
+/// line that is not empty.
+///
+/// Nothing is kept around between lines, so the headers and footer of a file
+/// can be read without holding every note of it in memory. Like
+/// [`ChuniChart::parse`][crate::ChuniChart::parse], it panics on lines that
+/// can't be parsed.
+pub struct C2sEvents<R> {
+    reader: R,
+    line: String,
+    notes: bool,
+    version: ChartVersion,
+}
+
+impl<R: BufRead> C2sEvents<R> {
+    pub fn new(reader: R) -> C2sEvents<R> {
+        C2sEvents {
+            reader,
+            line: String::new(),
+            notes: true,
+            version: ChartVersion::default(),
+        }
+    }
+
+    /// Skips note lines without parsing them, for when only the headers and
+    /// the footer are needed.
+    pub fn skip_notes(mut self) -> C2sEvents<R> {
+        self.notes = false;
+        self
+    }
+}
+
+impl<R: BufRead> Iterator for C2sEvents<R> {
+    type Item = io::Result<C2sEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+
+            let line = self.line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if !self.notes && matches!(ParserContext::get_section(line), ParserContext::Note) {
+                continue;
+            }
+
+            let event = C2sEvent::parse_versioned(line, self.version).unwrap();
+            if let C2sEvent::Header(Header::Version(version)) = event {
+                self.version = version;
+            }
+
+            return Some(Ok(event.into_owned()));
+        }
+    }
+}
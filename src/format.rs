@@ -0,0 +1,98 @@
+use std::string::ParseError;
+
+use crate::{events::ExtraLine, note::NoteType, ChuniChart, ParserContext};
+
+/// The order notes sharing the same position, cell and width are written in
+/// by the game's own charts. Ground notes come before the air notes that
+/// leech off of them.
+const TAG_ORDER: [&str; 20] = [
+    "TAP", "CHR", "HLD", "HXD", "SLD", "SLC", "SXD", "SXC", "FLK", "MNE", "AIR", "AUR", "AUL",
+    "AHD", "ADW", "ADR", "ADL", "ASD", "ASC", "ALD",
+];
+
+impl NoteType {
+    /// The key notes are sorted by in canonical `.c2s` files: measure,
+    /// offset, cell, width and tag, followed by the duration and end
+    /// position for notes that share all of those.
+    pub fn sort_key(&self) -> [usize; 8] {
+        let tag = TAG_ORDER
+            .iter()
+            .position(|x| *x == self.tag())
+            .unwrap_or(TAG_ORDER.len());
+
+        let (end_cell, end_width) = self.end().unwrap_or_default();
+
+        [
+            self.measure(),
+            self.offset(),
+            self.cell(),
+            self.width(),
+            tag,
+            self.duration().unwrap_or_default(),
+            end_cell,
+            end_width,
+        ]
+    }
+}
+
+impl ChuniChart {
+    /// Sorts the BPM, time signature and playfield speed designations by
+    /// position, and the notes in the order the game's own charts use.
+    pub fn sort(&mut self) {
+        self.bpm.sort_by_key(|x| (x.beginning_measure, x.offset));
+        self.met.sort_by_key(|x| (x.beginning_measure, x.offset));
+        self.sfl.sort_by_key(|x| (x.beginning_measure, x.offset));
+        self.notes.sort_by_key(|x| x.sort_key());
+    }
+}
+
+/// Rewrites a `.c2s` file in the canonical layout used by the game: the
+/// header block, the BPM/MET/SFL block, the notes sorted with
+/// [`NoteType::sort_key`], and then the footer.
+///
+/// Fields may be separated by any whitespace, and numbers may be written with
+/// any precision. The `T_*` statistics are kept in the footer in their
+/// original order, while other lines that are not understood by the parser
+/// stay where they were, after the same number of chart lines as in the
+/// input. Those and the `CREATOR` line are free text, so only the whitespace
+/// after their tag is rewritten.
+///
+/// Returns a [`ParseError`] if parsing the chart fails.
+pub fn format(input: &str) -> Result<String, ParseError> {
+    let body = input
+        .lines()
+        .map(normalize)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut chart = ChuniChart::parse(body)?;
+    chart.sort();
+
+    // extra lines are written in order, so the statistics moved past every
+    // other line have to be sorted there too.
+    for extra in &mut chart.extra {
+        if let ExtraLine::Statistic(_) = extra.line {
+            extra.header = false;
+            extra.position = usize::MAX;
+        }
+    }
+    chart.extra.sort_by_key(|x| (!x.header, x.position));
+
+    Ok(chart.to_string())
+}
+
+/// Separates the fields of a line with single tabs. The text after the tag
+/// of free-text lines is kept as it is, spaces included.
+fn normalize(line: &str) -> String {
+    let line = line.trim();
+    let tag = line.split_whitespace().next().unwrap_or_default();
+
+    match ParserContext::get_section(tag) {
+        ParserContext::Creator | ParserContext::None => match line[tag.len()..].trim_start() {
+            "" => tag.to_string(),
+            text => format!("{}\t{}", tag, text),
+        },
+        _ => line.split_whitespace().collect::<Vec<_>>().join("\t"),
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use crate::{
+    json::{Json, ToJson},
+    ChuniChart,
+};
+
+/// How close the scroll speed after a BPM change has to be to the one
+/// before it for the change to count as cancelled, relative to the latter.
+const CANCEL_TOLERANCE: f64 = 0.01;
+
+/// A kind of scroll speed gimmick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GimmickKind {
+    /// Notes scroll faster or slower than normal.
+    SpeedChange,
+    /// Notes stop scrolling.
+    Stop,
+    /// Notes scroll backwards.
+    Reverse,
+    /// The BPM changes, but an SFL designation keeps the notes scrolling at
+    /// the same speed, so players can't see it coming.
+    CancelledBpm,
+}
+
+impl fmt::Display for GimmickKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GimmickKind::SpeedChange => f.write_str("speed change"),
+            GimmickKind::Stop => f.write_str("stop"),
+            GimmickKind::Reverse => f.write_str("reverse"),
+            GimmickKind::CancelledBpm => f.write_str("cancelled bpm"),
+        }
+    }
+}
+
+/// A part of a chart where the notes do not scroll as the BPM suggests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gimmick {
+    pub kind: GimmickKind,
+    /// The measure the gimmick starts in.
+    pub measure: usize,
+    /// The offset the gimmick starts at.
+    pub offset: usize,
+    /// The position the gimmick starts at.
+    pub start_tick: usize,
+    /// The position the gimmick ends at. BPM changes end where they start.
+    pub end_tick: usize,
+    /// The BPM at the start of the gimmick.
+    pub bpm: f64,
+    /// The scroll speed multiplier at the start of the gimmick.
+    pub multiplier: f64,
+}
+
+/// A note that is on screen for less than
+/// [`GimmickOptions::min_approach_time`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastNote {
+    pub measure: usize,
+    pub offset: usize,
+    /// The index of the note in [`ChuniChart`]'s `notes`.
+    pub note: usize,
+    /// How long the note is on screen before reaching the judgement line, in
+    /// milliseconds.
+    pub approach_time: f64,
+}
+
+/// What a [`GimmickReport`] considers readable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GimmickOptions {
+    /// How much of the chart the lane shows at normal speed, in measures.
+    pub lane_length: f64,
+    /// The shortest time a note can be on screen before it is reported, in
+    /// milliseconds.
+    pub min_approach_time: f64,
+}
+
+impl Default for GimmickOptions {
+    /// A lane of a measure, and notes on screen for at least 300ms.
+    fn default() -> Self {
+        GimmickOptions {
+            lane_length: 1.0,
+            min_approach_time: 300.0,
+        }
+    }
+}
+
+/// The scroll speed gimmicks of a chart, and the notes they make hard to
+/// read.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GimmickReport {
+    /// Every gimmick, sorted by position.
+    pub gimmicks: Vec<Gimmick>,
+    /// Every note on screen for too short, in the order of the chart.
+    pub fast_notes: Vec<FastNote>,
+}
+
+impl ChuniChart {
+    /// Lists where SFL designations change the scroll speed, stop or
+    /// reverse the notes, and where they cancel out a BPM change, along
+    /// with the notes that are not on screen long enough to be read.
+    pub fn gimmicks(&self, options: &GimmickOptions) -> GimmickReport {
+        let scroll = self.scroll_map();
+        let tempo = scroll.tempo_map();
+
+        let mut gimmicks = scroll
+            .changes()
+            .into_iter()
+            .map(|(ticks, multiplier)| Gimmick {
+                kind: if multiplier < 0.0 {
+                    GimmickKind::Reverse
+                } else if multiplier == 0.0 {
+                    GimmickKind::Stop
+                } else {
+                    GimmickKind::SpeedChange
+                },
+                measure: ticks.start / self.resolution,
+                offset: ticks.start % self.resolution,
+                start_tick: ticks.start,
+                end_tick: ticks.end,
+                bpm: tempo.bpm(ticks.start),
+                multiplier,
+            })
+            .collect::<Vec<_>>();
+
+        for bpm in &self.bpm {
+            let tick = bpm.beginning_measure * self.resolution + bpm.offset;
+            let Some(before) = tick.checked_sub(1) else {
+                continue;
+            };
+
+            let speed = tempo.bpm(before) * scroll.multiplier(before);
+            if tempo.bpm(before) == bpm.bpm || speed == 0.0 {
+                continue;
+            }
+
+            let multiplier = scroll.multiplier(tick);
+            if (bpm.bpm * multiplier - speed).abs() <= speed.abs() * CANCEL_TOLERANCE {
+                gimmicks.push(Gimmick {
+                    kind: GimmickKind::CancelledBpm,
+                    measure: bpm.beginning_measure,
+                    offset: bpm.offset,
+                    start_tick: tick,
+                    end_tick: tick,
+                    bpm: bpm.bpm,
+                    multiplier,
+                });
+            }
+        }
+        gimmicks.sort_by_key(|x| x.start_tick);
+
+        let lane_length = options.lane_length * self.resolution as f64;
+        let fast_notes = self
+            .notes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, note)| {
+                let approach_time = scroll.approach_time(note.tick(self.resolution), lane_length);
+                (approach_time < options.min_approach_time).then(|| FastNote {
+                    measure: note.measure(),
+                    offset: note.offset(),
+                    note: i,
+                    approach_time,
+                })
+            })
+            .collect();
+
+        GimmickReport {
+            gimmicks,
+            fast_notes,
+        }
+    }
+}
+
+impl ToJson for Gimmick {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("kind", self.kind.to_string().into()),
+            ("measure", self.measure.into()),
+            ("offset", self.offset.into()),
+            ("start_tick", self.start_tick.into()),
+            ("end_tick", self.end_tick.into()),
+            ("bpm", self.bpm.into()),
+            ("multiplier", self.multiplier.into()),
+        ])
+    }
+}
+
+impl ToJson for FastNote {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("measure", self.measure.into()),
+            ("offset", self.offset.into()),
+            ("note", self.note.into()),
+            ("approach_time", self.approach_time.into()),
+        ])
+    }
+}
+
+impl ToJson for GimmickReport {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("gimmicks", self.gimmicks.to_json()),
+            ("fast_notes", self.fast_notes.to_json()),
+        ])
+    }
+}
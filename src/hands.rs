@@ -0,0 +1,476 @@
+use std::{collections::HashMap, fmt, iter};
+
+use crate::{
+    json::{Json, ToJson},
+    note::NoteType,
+    ChuniChart,
+};
+
+/// The widest span of cells a single hand can cover at once.
+const HAND_SPAN: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hand::Left => f.write_str("left"),
+            Hand::Right => f.write_str("right"),
+        }
+    }
+}
+
+/// A position where more than two hands are needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandConflict {
+    pub measure: usize,
+    pub offset: usize,
+    /// The indices in [`ChuniChart`]'s `notes` of the notes hit at this
+    /// position.
+    pub notes: Vec<usize>,
+    /// The amount of hands needed, counting the ones still holding a Hold or
+    /// a Slide.
+    pub hands: usize,
+}
+
+/// The hand every ground note is played with.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HandAssignment {
+    /// The hand of every note, by index in [`ChuniChart`]'s `notes`. Air
+    /// notes, Mines and notes that no hand was free for are `None`.
+    pub hands: Vec<Option<Hand>>,
+    pub conflicts: Vec<HandConflict>,
+}
+
+impl HandAssignment {
+    /// Amount of notes played with the given hand.
+    pub fn count(&self, hand: Hand) -> usize {
+        self.hands.iter().filter(|x| **x == Some(hand)).count()
+    }
+}
+
+/// What a hand is doing.
+#[derive(Debug, Clone)]
+struct HandState {
+    /// The indices of the Holds and Slides the hand is holding.
+    held: Vec<usize>,
+    /// The center of the last cells the hand touched.
+    position: f64,
+}
+
+impl NoteType {
+    /// Whether or not the note is played with a hand on the slider.
+    fn is_played(&self) -> bool {
+        matches!(
+            self,
+            NoteType::Tap(_)
+                | NoteType::ExTap(_)
+                | NoteType::Flick(_)
+                | NoteType::Hold(_)
+                | NoteType::HoldWithExTapHead(_)
+                | NoteType::Slide(_)
+                | NoteType::SlideControlPoint(_)
+                | NoteType::SlideWithExTapHead(_)
+                | NoteType::SlideControlPointWithExTapHead(_)
+        )
+    }
+
+    /// The first and the last cell the note covers at the given position.
+    /// Slides move from their start to their end cells along the way.
+    fn span(&self, tick: usize, resolution: usize) -> (f64, f64) {
+        let (cell, width) = (self.cell() as f64, self.width().max(1) as f64);
+        let (cell, width) = match (self.end(), self.duration()) {
+            (Some((end_cell, end_width)), Some(duration)) if duration > 0 => {
+                let progress = tick.saturating_sub(self.tick(resolution)) as f64 / duration as f64;
+                let progress = progress.min(1.0);
+                (
+                    cell + (end_cell as f64 - cell) * progress,
+                    width + (end_width.max(1) as f64 - width) * progress,
+                )
+            }
+            _ => (cell, width),
+        };
+
+        (cell, cell + width - 1.0)
+    }
+}
+
+/// The center of the given spans.
+fn center(spans: &[(f64, f64)]) -> f64 {
+    spans
+        .iter()
+        .map(|(start, end)| (start + end + 1.0) / 2.0)
+        .sum::<f64>()
+        / spans.len() as f64
+}
+
+/// Whether or not a single hand can reach every one of the spans, which is
+/// the case when the cells between them fit in its span.
+fn reachable(spans: &[(f64, f64)]) -> bool {
+    let (start, end) = spans.iter().fold((f64::MIN, f64::MAX), |(start, end), x| {
+        (start.max(x.0), end.min(x.1))
+    });
+
+    start < end + HAND_SPAN as f64
+}
+
+/// Both hands while a chart is played.
+struct Player<'a> {
+    chart: &'a ChuniChart,
+    left: HandState,
+    right: HandState,
+    /// The hand following every slide chain, by where it continues.
+    chains: HashMap<(usize, usize, usize), Hand>,
+    hands: Vec<Option<Hand>>,
+}
+
+impl Player<'_> {
+    fn state(&self, hand: Hand) -> &HandState {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+
+    fn state_mut(&mut self, hand: Hand) -> &mut HandState {
+        match hand {
+            Hand::Left => &mut self.left,
+            Hand::Right => &mut self.right,
+        }
+    }
+
+    /// The cells each of the notes covers at the given position.
+    fn spans(&self, notes: &[usize], tick: usize) -> Vec<(f64, f64)> {
+        notes
+            .iter()
+            .map(|i| self.chart.notes[*i].span(tick, self.chart.resolution))
+            .collect()
+    }
+
+    /// Lets go of every note that has ended at the given position.
+    fn release(&mut self, tick: usize) {
+        let resolution = self.chart.resolution;
+        let notes = &self.chart.notes;
+
+        for state in [&mut self.left, &mut self.right] {
+            state.held.retain(|i| {
+                notes[*i].tick(resolution) + notes[*i].duration().unwrap_or_default() > tick
+            });
+        }
+    }
+
+    /// The center of the cells the hand is at.
+    fn position(&self, hand: Hand, tick: usize) -> f64 {
+        let state = self.state(hand);
+        match state.held.is_empty() {
+            true => state.position,
+            false => center(&self.spans(&state.held, tick)),
+        }
+    }
+
+    /// The position the `i`th note of the chart ends at.
+    fn end(&self, i: usize) -> usize {
+        let note = &self.chart.notes[i];
+        note.tick(self.chart.resolution) + note.duration().unwrap_or_default()
+    }
+
+    /// Whether or not a single hand can hit the notes at the given position
+    /// and keep holding them until they end.
+    fn reaches_all(&self, notes: &[usize], tick: usize) -> bool {
+        let ends = notes.iter().map(|i| self.end(*i)).filter(|end| *end > tick);
+
+        iter::once(tick).chain(ends).all(|at| {
+            let active = notes
+                .iter()
+                .filter(|i| at == tick || self.end(**i) >= at)
+                .copied()
+                .collect::<Vec<_>>();
+
+            reachable(&self.spans(&active, at))
+        })
+    }
+
+    /// Whether or not the hand can hit the notes while holding the ones it
+    /// already holds.
+    fn reaches(&self, hand: Hand, notes: &[usize], tick: usize) -> bool {
+        self.reaches_all(&[&self.state(hand).held[..], notes].concat(), tick)
+    }
+
+    /// Splits notes hit at the same position into at most two groups that a
+    /// single hand can reach, from left to right.
+    ///
+    /// Every note starts in its own group, and the closest groups are merged
+    /// until there are two left. Of groups as close to each other, the ones
+    /// held the longest are merged, leaving a hand free sooner.
+    fn groups(&self, notes: &[usize], tick: usize) -> Vec<Vec<usize>> {
+        let center_of = |group: &[usize]| center(&self.spans(group, tick));
+
+        // notes starting at the same cells are sorted by where they end, so
+        // slides moving apart end up in different groups.
+        let moved = |group: &[usize]| center(&self.spans(group, self.end(group[0])));
+        let mut groups = notes.iter().map(|i| vec![*i]).collect::<Vec<_>>();
+        groups.sort_by(|a, b| {
+            center_of(a)
+                .total_cmp(&center_of(b))
+                .then_with(|| moved(a).total_cmp(&moved(b)))
+        });
+
+        while groups.len() > 2 {
+            let gap = |i: usize| center_of(&groups[i]) - center_of(&groups[i - 1]);
+            let release = |i: usize| {
+                groups[i - 1]
+                    .iter()
+                    .chain(&groups[i])
+                    .map(|x| self.end(*x))
+                    .min()
+            };
+
+            let merged = (1..groups.len())
+                .filter(|i| self.reaches_all(&[&groups[i - 1][..], &groups[*i]].concat(), tick))
+                .min_by(|a, b| {
+                    gap(*a)
+                        .total_cmp(&gap(*b))
+                        .then_with(|| release(*b).cmp(&release(*a)))
+                });
+
+            match merged {
+                Some(i) => {
+                    let group = groups.remove(i);
+                    groups[i - 1].extend(group);
+                }
+                None => break,
+            }
+        }
+
+        groups
+    }
+
+    /// Picks the hand that hits each group, leaving out the groups no hand
+    /// can reach.
+    fn assign<'a>(&self, groups: &'a [Vec<usize>], tick: usize) -> Vec<(&'a [usize], Hand)> {
+        let fits = |group: &[usize], hand: Hand| self.reaches(hand, group, tick);
+        let distance = |group: &[usize], hand: Hand| {
+            (center(&self.spans(group, tick)) - self.position(hand, tick)).abs()
+        };
+
+        match groups {
+            [] => vec![],
+            [group] => {
+                let side = match center(&self.spans(group, tick)) < 8.0 {
+                    true => Hand::Left,
+                    false => Hand::Right,
+                };
+
+                [Hand::Left, Hand::Right]
+                    .into_iter()
+                    .filter(|hand| fits(group, *hand))
+                    .min_by(|a, b| {
+                        distance(group, *a)
+                            .total_cmp(&distance(group, *b))
+                            .then_with(|| (*a != side).cmp(&(*b != side)))
+                    })
+                    .map(|hand| vec![(&group[..], hand)])
+                    .unwrap_or_default()
+            }
+            [left, right] if fits(left, Hand::Left) && fits(right, Hand::Right) => {
+                vec![(left, Hand::Left), (right, Hand::Right)]
+            }
+            [left, right] if fits(left, Hand::Right) && fits(right, Hand::Left) => {
+                vec![(left, Hand::Right), (right, Hand::Left)]
+            }
+            _ => {
+                // each hand takes the outermost group on its side it can
+                // reach.
+                let left = groups.iter().find(|group| fits(group, Hand::Left));
+                let right = groups
+                    .iter()
+                    .rfind(|group| fits(group, Hand::Right) && Some(*group) != left);
+
+                [(left, Hand::Left), (right, Hand::Right)]
+                    .into_iter()
+                    .filter_map(|(group, hand)| Some((&group?[..], hand)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Groups the notes hit at the same position, splitting them between
+    /// the hands when they can be, or giving all of them to one hand when
+    /// only a single hand can hit them.
+    fn arrange(&self, notes: &[usize], tick: usize) -> Vec<Vec<usize>> {
+        let groups = self.groups(notes, tick);
+        if groups.len() > 1 && self.assign(&groups, tick).len() < groups.len() {
+            let whole = vec![notes.to_vec()];
+            if !self.assign(&whole, tick).is_empty() {
+                return whole;
+            }
+        }
+
+        groups
+    }
+
+    /// Moves every note held by the hand closest to the given notes to the
+    /// other hand, if it can reach all of them. Returns whether or not a
+    /// hand was freed.
+    fn hand_over(&mut self, notes: &[usize], tick: usize) -> bool {
+        let held = [&self.left.held[..], &self.right.held[..]].concat();
+        if self.left.held.is_empty() || self.right.held.is_empty() || !self.reaches_all(&held, tick)
+        {
+            return false;
+        }
+
+        let center = center(&self.spans(notes, tick));
+        let (from, to) = match (self.position(Hand::Left, tick) - center).abs()
+            < (self.position(Hand::Right, tick) - center).abs()
+        {
+            true => (Hand::Left, Hand::Right),
+            false => (Hand::Right, Hand::Left),
+        };
+
+        let moved = std::mem::take(&mut self.state_mut(from).held);
+        self.state_mut(to).held.extend(moved);
+        true
+    }
+
+    /// Plays the `i`th note of the chart with the given hand.
+    fn play(&mut self, i: usize, hand: Hand) {
+        let note = &self.chart.notes[i];
+        let end = self.end(i);
+        let position = center(&[note.span(end, self.chart.resolution)]);
+
+        let state = self.state_mut(hand);
+        if note.duration().is_some() {
+            state.held.push(i);
+        }
+        state.position = position;
+        self.hands[i] = Some(hand);
+
+        if let Some(slide) = note.slide() {
+            self.chains
+                .insert((end, slide.end_cell, slide.end_width), hand);
+        }
+    }
+}
+
+impl ChuniChart {
+    /// Assigns every ground note to the left or the right hand.
+    ///
+    /// Notes are played in order. Holds and Slides keep their hand busy
+    /// until they end, and the segments of a slide chain stay on the hand
+    /// that started it. A hand can hit other notes while holding one, as
+    /// long as it can reach all of them. Notes hit at the same time are
+    /// split between both hands when they can be, the leftmost going to the
+    /// left hand, and a lone note goes to the closest hand that can reach
+    /// it.
+    ///
+    /// When the notes hit at a position can't be split between the hands
+    /// that reach them, the position is reported as a [`HandConflict`], and
+    /// the notes left over get no hand.
+    pub fn hands(&self) -> HandAssignment {
+        let resolution = self.resolution;
+        let slide_ends = self.slide_ends();
+
+        let mut order = (0..self.notes.len())
+            .filter(|i| self.notes[*i].is_played())
+            .collect::<Vec<_>>();
+        order.sort_by_key(|i| self.notes[*i].tick(resolution));
+
+        let mut player = Player {
+            chart: self,
+            left: HandState {
+                held: vec![],
+                position: 4.0,
+            },
+            right: HandState {
+                held: vec![],
+                position: 12.0,
+            },
+            chains: HashMap::new(),
+            hands: vec![None; self.notes.len()],
+        };
+        let mut conflicts = vec![];
+
+        for step in order
+            .chunk_by(|a, b| self.notes[*a].tick(resolution) == self.notes[*b].tick(resolution))
+        {
+            let tick = self.notes[step[0]].tick(resolution);
+            player.release(tick);
+
+            let mut heads = vec![];
+            for i in step {
+                let note = &self.notes[*i];
+                let key = (tick, note.cell(), note.width());
+                let chain = match note.slide() {
+                    Some(_) if slide_ends.contains(&key) => player.chains.remove(&key),
+                    _ => None,
+                };
+
+                match chain {
+                    Some(hand) => player.play(*i, hand),
+                    None => heads.push(*i),
+                }
+            }
+
+            // when no hand is free for the notes, one hand may take over
+            // what the other one holds.
+            let mut groups = player.arrange(&heads, tick);
+            if player.assign(&groups, tick).len() < groups.len() && player.hand_over(&heads, tick) {
+                groups = player.arrange(&heads, tick);
+            }
+
+            let plays = player.assign(&groups, tick);
+            let missed = groups.len() - plays.len();
+            if missed > 0 {
+                conflicts.push(HandConflict {
+                    measure: tick / resolution,
+                    offset: tick % resolution,
+                    notes: step.to_vec(),
+                    hands: 2 + missed,
+                });
+            }
+
+            let plays = plays
+                .into_iter()
+                .flat_map(|(group, hand)| group.iter().map(move |i| (*i, hand)))
+                .collect::<Vec<_>>();
+            for (i, hand) in plays {
+                player.play(i, hand);
+            }
+        }
+
+        HandAssignment {
+            hands: player.hands,
+            conflicts,
+        }
+    }
+}
+
+impl ToJson for HandConflict {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("measure", self.measure.into()),
+            ("offset", self.offset.into()),
+            ("notes", self.notes.clone().into()),
+            ("hands", self.hands.into()),
+        ])
+    }
+}
+
+impl ToJson for HandAssignment {
+    fn to_json(&self) -> Json {
+        let hands = self
+            .hands
+            .iter()
+            .map(|x| x.map(|hand| hand.to_string()))
+            .collect::<Vec<_>>();
+
+        Json::object([
+            ("hands", hands.into()),
+            ("conflicts", self.conflicts.to_json()),
+        ])
+    }
+}
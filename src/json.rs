@@ -0,0 +1,314 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    bpm::{Bpm, BpmDef},
+    met::{Met, MetDef, TimeSignature},
+    note::NoteType,
+    sfl::Sfl,
+    ChuniChart,
+};
+
+/// A JSON value. Formatting it with `{}` writes compact JSON, while `{:#}`
+/// writes it indented.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys are kept in insertion order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds a [`Json::Object`] from a list of key-value pairs.
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let pretty = f.alternate();
+        let newline = |f: &mut fmt::Formatter<'_>, indent: usize| {
+            if pretty {
+                write!(f, "\n{:width$}", "", width = indent * 2)
+            } else {
+                Ok(())
+            }
+        };
+
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => f.write_str("null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                if values.is_empty() {
+                    return f.write_str("[]");
+                }
+
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    newline(f, indent + 1)?;
+                    value.write(f, indent + 1)?;
+                }
+                newline(f, indent)?;
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                if fields.is_empty() {
+                    return f.write_str("{}");
+                }
+
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    newline(f, indent + 1)?;
+                    write_string(f, key)?;
+                    f.write_str(if pretty { ": " } else { ":" })?;
+                    value.write(f, indent + 1)?;
+                }
+                newline(f, indent)?;
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(value: Vec<T>) -> Self {
+        Json::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Conversion of chart data into [`Json`].
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl ToJson for BpmDef {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("starting_bpm", self.starting_bpm.into()),
+            ("mode", self.mode.into()),
+            ("highest_bpm", self.highest_bpm.into()),
+            ("lowest_bpm", self.lowest_bpm.into()),
+        ])
+    }
+}
+
+impl ToJson for Bpm {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("beginning_measure", self.beginning_measure.into()),
+            ("offset", self.offset.into()),
+            ("bpm", self.bpm.into()),
+        ])
+    }
+}
+
+impl ToJson for TimeSignature {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("numerator", self.numerator.into()),
+            ("denominator", self.denominator.into()),
+        ])
+    }
+}
+
+impl ToJson for MetDef {
+    fn to_json(&self) -> Json {
+        self.signature.to_json()
+    }
+}
+
+impl ToJson for Met {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("beginning_measure", self.beginning_measure.into()),
+            ("offset", self.offset.into()),
+            ("numerator", self.signature.numerator.into()),
+            ("denominator", self.signature.denominator.into()),
+        ])
+    }
+}
+
+impl ToJson for Sfl {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("beginning_measure", self.beginning_measure.into()),
+            ("offset", self.offset.into()),
+            ("duration", self.duration.into()),
+            ("multiplier", self.multiplier.into()),
+        ])
+    }
+}
+
+impl ToJson for NoteType {
+    /// Notes are written as objects with a `type` field holding their `.c2s`
+    /// tag, followed by the fields of the note.
+    fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("type".to_string(), self.tag().into()),
+            ("measure".to_string(), self.measure().into()),
+            ("offset".to_string(), self.offset().into()),
+            ("cell".to_string(), self.cell().into()),
+            ("width".to_string(), self.width().into()),
+        ];
+
+        let extra = match self {
+            NoteType::Tap(_) | NoteType::Mine(_) => vec![],
+            NoteType::ExTap(note) => vec![("animation", note.animation.as_str().into())],
+            NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => vec![
+                ("duration", note.duration.into()),
+                ("animation", note.animation.clone().into()),
+            ],
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => vec![
+                ("duration", note.duration.into()),
+                ("end_cell", note.end_cell.into()),
+                ("end_width", note.end_width.into()),
+                ("animation", note.animation.clone().into()),
+            ],
+            NoteType::Flick(note) => vec![("unknown", note.unknown.as_str().into())],
+            NoteType::Air(note)
+            | NoteType::AirUpRight(note)
+            | NoteType::AirUpLeft(note)
+            | NoteType::AirDown(note)
+            | NoteType::AirDownRight(note)
+            | NoteType::AirDownLeft(note) => {
+                vec![("target_note", note.target_note.as_str().into())]
+            }
+            NoteType::AirHold(note) => vec![
+                ("target_note", note.target_note.as_str().into()),
+                ("duration", note.duration.into()),
+            ],
+            NoteType::AirCrush(note) => vec![
+                ("unknown", note.unknown.into()),
+                ("starting_height", note.starting_height.into()),
+                ("duration", note.duration.into()),
+                ("end_cell", note.end_cell.into()),
+                ("end_width", note.end_width.into()),
+                ("target_height", note.target_height.into()),
+                ("color", note.color.as_str().into()),
+            ],
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => vec![
+                ("target_note", note.target_note.as_str().into()),
+                ("starting_height", note.starting_height.into()),
+                ("duration", note.duration.into()),
+                ("end_cell", note.end_cell.into()),
+                ("end_width", note.end_width.into()),
+                ("target_height", note.target_height.into()),
+                ("color", note.color.as_str().into()),
+            ],
+        };
+
+        fields.extend(
+            extra
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value)),
+        );
+        Json::Object(fields)
+    }
+}
+
+impl ToJson for ChuniChart {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("version", self.version.first.to_string().into()),
+            ("music", self.music.into()),
+            ("sequence_id", self.sequence_id.into()),
+            ("difficult", self.difficult.into()),
+            ("level", self.level.into()),
+            ("creator", self.creator.as_str().into()),
+            ("bpm_def", self.bpm_def.to_json()),
+            ("met_def", self.met_def.to_json()),
+            ("resolution", self.resolution.into()),
+            ("clk_def", self.clk_def.into()),
+            ("progjudge_bpm", self.progjudge_bpm.into()),
+            ("progjudge_aer", self.progjudge_aer.into()),
+            ("tutorial", self.tutorial.into()),
+            ("bpm", self.bpm.to_json()),
+            ("met", self.met.to_json()),
+            ("sfl", self.sfl.to_json()),
+            ("notes", self.notes.to_json()),
+        ])
+    }
+}
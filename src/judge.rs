@@ -0,0 +1,566 @@
+use std::{fmt, iter, str::FromStr, string::ParseError};
+
+use crate::{
+    autoplay::{AirInput, InputFrame},
+    json::{Json, ToJson},
+    note::NoteType,
+    ChuniChart,
+};
+
+/// The state of all 6 beams of the air sensor, with the lowest beam at bit 0.
+pub const ALL_BEAMS: u8 = 0b111111;
+
+/// The step used when checking that Holds, Slides and AirHolds are held, in
+/// milliseconds.
+const STEP: f64 = 5.0;
+
+/// A change in the state of the controller, as captured from a cabinet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+    /// The time of the change, in milliseconds from the start of the chart.
+    pub time: f64,
+    pub input: Input,
+}
+
+/// The full state of one of the sensors of the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// The touch pads that are held, with the same layout as
+    /// [`InputFrame::pads`].
+    Slider(u32),
+    /// The beams of the air sensor that are blocked, with the lowest beam at
+    /// bit 0.
+    Air(u8),
+}
+
+/// Parses a line of an input log: the time in milliseconds, `slider` or
+/// `air`, and the state of the sensor in hexadecimal.
+///
+/// ```text
+/// 1666.667 slider 30000
+/// 2051.282 air 3f
+/// ```
+impl FromStr for InputEvent {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s.split_whitespace().collect::<Vec<_>>();
+        let [time, sensor, state] = values[..] else {
+            unreachable!("invalid input event found. bailing.")
+        };
+
+        let time = time.parse().unwrap();
+        let input = match sensor {
+            "slider" => Input::Slider(u32::from_str_radix(state, 16).unwrap()),
+            "air" => Input::Air(u8::from_str_radix(state, 16).unwrap()),
+            _ => unreachable!("invalid input sensor found. bailing."),
+        };
+
+        Ok(InputEvent { time, input })
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.input {
+            Input::Slider(pads) => write!(f, "{:.3} slider {:x}", self.time, pads),
+            Input::Air(beams) => write!(f, "{:.3} air {:x}", self.time, beams),
+        }
+    }
+}
+
+impl InputEvent {
+    /// Parses an input log, with one [`InputEvent`] per line. Empty lines are
+    /// ignored.
+    pub fn parse_log(input: &str) -> Result<Vec<InputEvent>, ParseError> {
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(InputEvent::from_str)
+            .collect()
+    }
+
+    /// Turns the timeline of [`ChuniChart::autoplay`] into input events, as
+    /// they would be captured from a controller following it.
+    ///
+    /// Air notes block every beam. The beams are cleared right before an
+    /// air up note, so consecutive notes are seen as separate motions, and
+    /// halfway through an air down note.
+    pub fn from_autoplay(frames: &[InputFrame]) -> Vec<InputEvent> {
+        let mut events = vec![];
+        let mut pads = 0;
+        let mut beams = 0;
+
+        for frame in frames {
+            if frame.pads != pads {
+                pads = frame.pads;
+                events.push(InputEvent {
+                    time: frame.start,
+                    input: Input::Slider(pads),
+                });
+            }
+
+            let mut air = |time: f64, state: u8| {
+                if state != beams {
+                    beams = state;
+                    events.push(InputEvent {
+                        time,
+                        input: Input::Air(state),
+                    });
+                }
+            };
+
+            match frame.air {
+                AirInput::Idle => air(frame.start, 0),
+                AirInput::Held => air(frame.start, ALL_BEAMS),
+                AirInput::Rising => {
+                    air(frame.start, 0);
+                    air(frame.start, ALL_BEAMS);
+                }
+                AirInput::Falling => {
+                    air(frame.start, ALL_BEAMS);
+                    air((frame.start + frame.end) / 2.0, 0);
+                }
+            }
+        }
+
+        events
+    }
+}
+
+/// The timing windows notes are judged with, in milliseconds on either side
+/// of the note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingWindows {
+    pub justice_critical: f64,
+    pub justice: f64,
+    /// Inputs further away than this are ignored, and notes without an input
+    /// in this window are a MISS.
+    pub attack: f64,
+    /// How long Holds, Slides and AirHolds may be released for without
+    /// being a MISS.
+    pub release: f64,
+}
+
+impl Default for TimingWindows {
+    fn default() -> Self {
+        TimingWindows {
+            justice_critical: 33.0,
+            justice: 66.0,
+            attack: 100.0,
+            release: 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Judgement {
+    JusticeCritical,
+    Justice,
+    Attack,
+    Miss,
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Judgement::JusticeCritical => f.write_str("JUSTICE CRITICAL"),
+            Judgement::Justice => f.write_str("JUSTICE"),
+            Judgement::Attack => f.write_str("ATTACK"),
+            Judgement::Miss => f.write_str("MISS"),
+        }
+    }
+}
+
+/// The judgement of a single note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteJudgement {
+    /// The index of the note in [`ChuniChart`]'s `notes`.
+    pub note: usize,
+    pub judgement: Judgement,
+    /// How late the note was hit, in milliseconds. Negative values are
+    /// early. `None` for notes that were missed, or that are not hit at a
+    /// single point in time.
+    pub offset: Option<f64>,
+}
+
+/// The result of judging a play of a chart.
+///
+/// The counts are of judgements rather than notes, as Holds, Slides,
+/// AirHolds and AirSlides are judged several times, see
+/// [`ChuniChart::judgement_ticks`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct JudgeResult {
+    /// The judgement of every judged note, in chart order.
+    pub judgements: Vec<NoteJudgement>,
+    /// The amount of judgements in the chart.
+    pub max_combo: usize,
+    pub justice_critical: usize,
+    pub justice: usize,
+    pub attack: usize,
+    pub miss: usize,
+    /// The final score, out of 1,010,000.
+    pub score: usize,
+}
+
+/// Something that was hit at a point in time: a cell of the slider being
+/// touched, or the air sensor being entered or left.
+struct Hit {
+    time: f64,
+    cell: usize,
+    /// The time of the notes the hit was used for. A single hit can be used
+    /// for every note at that time, such as the heads of Slides that split
+    /// apart.
+    used: Option<f64>,
+}
+
+/// The inputs of a play, split into hits and the state of both sensors over
+/// time.
+struct Play {
+    touches: Vec<Hit>,
+    air_ups: Vec<Hit>,
+    air_downs: Vec<Hit>,
+    slider: Vec<(f64, u32)>,
+    air: Vec<(f64, u8)>,
+}
+
+impl Play {
+    fn new(inputs: &[InputEvent]) -> Play {
+        let mut inputs = inputs.to_vec();
+        inputs.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let mut play = Play {
+            touches: vec![],
+            air_ups: vec![],
+            air_downs: vec![],
+            slider: vec![(f64::MIN, 0)],
+            air: vec![(f64::MIN, 0)],
+        };
+
+        for event in inputs {
+            match event.input {
+                Input::Slider(pads) => {
+                    let (_, previous) = *play.slider.last().unwrap();
+                    for cell in 0..16 {
+                        let mask = 0b11 << (2 * cell);
+                        if previous & mask == 0 && pads & mask != 0 {
+                            play.touches.push(Hit {
+                                time: event.time,
+                                cell,
+                                used: None,
+                            });
+                        }
+                    }
+                    play.slider.push((event.time, pads));
+                }
+                Input::Air(beams) => {
+                    let (_, previous) = *play.air.last().unwrap();
+                    let hit = Hit {
+                        time: event.time,
+                        cell: 0,
+                        used: None,
+                    };
+                    if beams & !previous != 0 {
+                        play.air_ups.push(hit);
+                    } else if previous & !beams != 0 {
+                        play.air_downs.push(hit);
+                    }
+                    play.air.push((event.time, beams));
+                }
+            }
+        }
+
+        play
+    }
+
+    fn pads(&self, time: f64) -> u32 {
+        let i = self.slider.partition_point(|(at, _)| *at <= time);
+        self.slider[i.saturating_sub(1)].1
+    }
+
+    fn beams(&self, time: f64) -> u8 {
+        let i = self.air.partition_point(|(at, _)| *at <= time);
+        self.air[i.saturating_sub(1)].1
+    }
+}
+
+/// Finds the hit closest to `time` within `window` that is not used for
+/// another note, and uses it up. Returns how late it was.
+fn take(hits: &mut [Hit], time: f64, window: f64, cells: Option<(usize, usize)>) -> Option<f64> {
+    let hit = hits
+        .iter_mut()
+        .filter(|hit| hit.used.is_none_or(|x| x == time) && (hit.time - time).abs() <= window)
+        .filter(|hit| cells.is_none_or(|(cell, width)| (cell..cell + width).contains(&hit.cell)))
+        .min_by(|a, b| (a.time - time).abs().total_cmp(&(b.time - time).abs()))?;
+
+    hit.used = Some(time);
+    Some(hit.time - time)
+}
+
+/// The cells covered by a note, as a mask of slider pads.
+fn mask(cell: usize, width: usize) -> u32 {
+    (cell..(cell + width).min(16)).fold(0, |mask, cell| mask | 0b11 << (2 * cell))
+}
+
+impl TimingWindows {
+    /// Judges a note hit `offset` milliseconds late.
+    fn judge(&self, offset: Option<f64>, critical: bool) -> Judgement {
+        match offset.map(f64::abs) {
+            None => Judgement::Miss,
+            Some(_) if critical => Judgement::JusticeCritical,
+            Some(x) if x <= self.justice_critical => Judgement::JusticeCritical,
+            Some(x) if x <= self.justice => Judgement::Justice,
+            Some(_) => Judgement::Attack,
+        }
+    }
+
+    /// Whether or not `held` stays true during a note lasting from `start`
+    /// to `end`, apart from gaps shorter than the release window.
+    ///
+    /// The attack window at the start and the release window at the end are
+    /// not checked, so a late hit or an early release is not a MISS. Notes
+    /// too short for that only have to be held at some point, give or take
+    /// the attack window.
+    fn held(&self, start: f64, end: f64, held: impl Fn(f64) -> bool) -> bool {
+        let (from, to) = (start + self.attack, end - self.release);
+        let samples = |from: f64, to: f64| {
+            let steps = ((to - from) / STEP).ceil().max(0.0) as usize;
+            (0..=steps).map(move |i| (from + i as f64 * STEP).min(to))
+        };
+
+        if from >= to {
+            return samples(start - self.attack, end + self.attack).any(held);
+        }
+
+        let mut released = None;
+        let mut ever = false;
+        for time in samples(from, to) {
+            match (held(time), released) {
+                (true, _) => {
+                    released = None;
+                    ever = true;
+                }
+                (false, None) => released = Some(time),
+                (false, Some(since)) if time - since > self.release => return false,
+                _ => {}
+            }
+        }
+
+        ever
+    }
+}
+
+impl ChuniChart {
+    /// Judges a recorded play of the chart.
+    ///
+    /// Every note counted by [`Statistics::total`][crate::stats::Statistics::total],
+    /// and every AirSlide control point, is judged once:
+    ///
+    /// - Taps, Flicks and the heads of Holds and Slides are judged by how far
+    ///   the closest touch of one of their cells is from the note.
+    /// - ExTaps, and the ExTap heads of Holds and Slides, are always a
+    ///   JUSTICE CRITICAL when hit, as they are in game.
+    /// - Holds and Slides are a MISS if they are released for longer than
+    ///   the release window. Slide segments that continue a chain are only
+    ///   judged on being held, and may be held one cell off.
+    /// - Air notes are a JUSTICE CRITICAL when the air sensor is entered (or
+    ///   left, for air down notes) within the attack window, as they are in
+    ///   game, so their timing only tells a hit from a MISS. AirHolds and
+    ///   AirSlides are a JUSTICE CRITICAL when it is not left for longer
+    ///   than the release window, and AirSlide control points, which can
+    ///   start a chain of their own, are judged the same way.
+    /// - Mines are a MISS when one of their cells is held during the justice
+    ///   window, and a JUSTICE CRITICAL otherwise.
+    ///
+    /// The score counts every judgement of [`ChuniChart::judgement_ticks`]
+    /// instead, so it is as approximate as those are. The first judgement
+    /// of a note takes the judgement of the note, and the ones while it is
+    /// held are a JUSTICE CRITICAL, or a MISS for notes that were missed.
+    /// Mines only count when they are a MISS, without adding to the max
+    /// combo. Every judgement is worth the same, with a JUSTICE CRITICAL
+    /// worth 101% of a JUSTICE and an ATTACK 50%, for a maximum score of
+    /// 1,010,000.
+    pub fn judge(&self, inputs: &[InputEvent], windows: &TimingWindows) -> JudgeResult {
+        let tempo = self.tempo_map();
+        let mut play = Play::new(inputs);
+
+        let mut order = (0..self.notes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| self.notes[*i].tick(self.resolution));
+
+        let slide_ends = self.slide_ends();
+
+        let mut judgements = vec![];
+        for i in order {
+            let note = &self.notes[i];
+            let tick = note.tick(self.resolution);
+            let time = tempo.ms(tick);
+            let (cell, width) = (note.cell(), note.width());
+            let cells = Some((cell, width));
+            let hit = |play: &mut Play| take(&mut play.touches, time, windows.attack, cells);
+            let body = |play: &Play, start: usize, length: usize, follow: bool| {
+                let (end_cell, end_width) = note.end().unwrap_or((cell, width));
+                windows.held(time, tempo.ms(start + length), |at| {
+                    let progress = (tempo.tick(at) - start as f64) / length.max(1) as f64;
+                    let lerp = |from: usize, to: usize| {
+                        from as f64 + (to as f64 - from as f64) * progress.clamp(0.0, 1.0)
+                    };
+                    let (cell, width) = (
+                        lerp(cell, end_cell).round() as usize,
+                        lerp(width, end_width).round() as usize,
+                    );
+                    let mask = match follow {
+                        true => mask(cell.saturating_sub(1), width + 2),
+                        false => mask(cell, width),
+                    };
+                    play.pads(at) & mask != 0
+                })
+            };
+
+            let (judgement, offset) = match note {
+                NoteType::Tap(_) | NoteType::Flick(_) => {
+                    let offset = hit(&mut play);
+                    (windows.judge(offset, false), offset)
+                }
+                NoteType::ExTap(_) => {
+                    let offset = hit(&mut play);
+                    (windows.judge(offset, true), offset)
+                }
+                NoteType::Hold(hold) | NoteType::HoldWithExTapHead(hold) => {
+                    let offset = hit(&mut play);
+                    let critical = matches!(note, NoteType::HoldWithExTapHead(_));
+                    match body(&play, tick, hold.duration, false) {
+                        true => (windows.judge(offset, critical), offset),
+                        false => (Judgement::Miss, None),
+                    }
+                }
+                NoteType::Slide(slide)
+                | NoteType::SlideControlPoint(slide)
+                | NoteType::SlideWithExTapHead(slide)
+                | NoteType::SlideControlPointWithExTapHead(slide) => {
+                    let critical = matches!(
+                        note,
+                        NoteType::SlideWithExTapHead(_)
+                            | NoteType::SlideControlPointWithExTapHead(_)
+                    );
+                    let held = body(&play, tick, slide.duration, true);
+
+                    match slide_ends.contains(&(tick, cell, width)) {
+                        true if held => (Judgement::JusticeCritical, None),
+                        true => (Judgement::Miss, None),
+                        false => {
+                            let offset = hit(&mut play);
+                            match held {
+                                true => (windows.judge(offset, critical), offset),
+                                false => (Judgement::Miss, None),
+                            }
+                        }
+                    }
+                }
+                NoteType::Mine(_) => {
+                    let touched = play.slider.iter().any(|(at, pads)| {
+                        (at - time).abs() <= windows.justice && pads & mask(cell, width) != 0
+                    });
+                    match touched || play.pads(time) & mask(cell, width) != 0 {
+                        true => (Judgement::Miss, None),
+                        false => (Judgement::JusticeCritical, None),
+                    }
+                }
+                NoteType::Air(_) | NoteType::AirUpLeft(_) | NoteType::AirUpRight(_) => {
+                    let offset = take(&mut play.air_ups, time, windows.attack, None);
+                    (windows.judge(offset, true), offset)
+                }
+                NoteType::AirDown(_) | NoteType::AirDownLeft(_) | NoteType::AirDownRight(_) => {
+                    let offset = take(&mut play.air_downs, time, windows.attack, None);
+                    (windows.judge(offset, true), offset)
+                }
+                NoteType::AirHold(_)
+                | NoteType::AirSlide(_)
+                | NoteType::AirSlideControlPoint(_) => {
+                    let end = tempo.ms(tick + note.duration().unwrap_or_default());
+                    match windows.held(time, end, |at| play.beams(at) != 0) {
+                        true => (Judgement::JusticeCritical, None),
+                        false => (Judgement::Miss, None),
+                    }
+                }
+                NoteType::AirCrush(_) => continue,
+            };
+
+            judgements.push(NoteJudgement {
+                note: i,
+                judgement,
+                offset,
+            });
+        }
+
+        judgements.sort_by_key(|x| x.note);
+
+        let ticks = self.note_judgements();
+        let counts = judgements
+            .iter()
+            .map(|x| match (&self.notes[x.note], x.judgement) {
+                (NoteType::Mine(_), Judgement::Miss) => 1,
+                _ => ticks[x.note].len(),
+            })
+            .collect::<Vec<_>>();
+        JudgeResult::new(judgements, &counts, ticks.iter().map(Vec::len).sum())
+    }
+}
+
+impl JudgeResult {
+    /// Counts the judgements of every note, given how many times each of
+    /// them is judged.
+    fn new(judgements: Vec<NoteJudgement>, counts: &[usize], max_combo: usize) -> JudgeResult {
+        let mut result = JudgeResult {
+            max_combo,
+            ..Default::default()
+        };
+
+        for (note, count) in judgements.iter().zip(counts) {
+            let held = match note.judgement {
+                Judgement::Miss => Judgement::Miss,
+                _ => Judgement::JusticeCritical,
+            };
+            let judgements = iter::once(note.judgement)
+                .chain(iter::repeat(held))
+                .take(*count);
+
+            for judgement in judgements {
+                match judgement {
+                    Judgement::JusticeCritical => result.justice_critical += 1,
+                    Judgement::Justice => result.justice += 1,
+                    Judgement::Attack => result.attack += 1,
+                    Judgement::Miss => result.miss += 1,
+                }
+            }
+        }
+
+        result.score = result.counts().score(result.max_combo);
+
+        result.judgements = judgements;
+        result
+    }
+}
+
+impl ToJson for NoteJudgement {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("note", self.note.into()),
+            ("judgement", self.judgement.to_string().into()),
+            ("offset", self.offset.into()),
+        ])
+    }
+}
+
+impl ToJson for JudgeResult {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("max_combo", self.max_combo.into()),
+            ("justice_critical", self.justice_critical.into()),
+            ("justice", self.justice.into()),
+            ("attack", self.attack.into()),
+            ("miss", self.miss.into()),
+            ("score", self.score.into()),
+            ("judgements", self.judgements.to_json()),
+        ])
+    }
+}
@@ -1,14 +1,46 @@
-use std::{str::FromStr, string::ParseError};
+use std::{
+    fmt,
+    io::{self, BufRead},
+    str::FromStr,
+    string::ParseError,
+};
 
 use bpm::{Bpm, BpmDef};
+use events::{C2sEvent, C2sEvents, Extra, ExtraLine, Header};
 use met::{Met, MetDef};
 use note::NoteType;
 use sfl::Sfl;
+use span::SourceMap;
+use version::ChartVersion;
 
+pub mod autoplay;
 pub mod bpm;
+pub mod click;
+pub mod density;
+pub mod difficulty;
+pub mod events;
+pub mod format;
+pub mod gimmicks;
+pub mod hands;
+pub mod json;
+pub mod judge;
+pub mod library;
+pub mod lint;
 pub mod met;
+pub mod music_xml;
 pub mod note;
+pub mod patterns;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod score;
+pub mod scroll;
 pub mod sfl;
+pub mod span;
+pub mod stats;
+pub mod timing;
+pub mod transform;
+pub mod ugc;
+pub mod version;
 
 pub enum ParserContext {
     None,
@@ -52,8 +84,10 @@ impl FromStr for ParserContext {
             "BPM" => Self::Bpm,
             "MET" => Self::Met,
             "SFL" => Self::Sfl,
-            "TAP" | "CHR" | "HLD" | "HXD" | "SLD" | "SLC" | "SXC" | "FLK" | "AIR" | "AUR"
-            | "AUL" | "AHD" | "ADW" | "ADR" | "ADL" | "ALD" | "MNE" => Self::Note,
+            "TAP" | "CHR" | "HLD" | "HXD" | "SLD" | "SLC" | "SXD" | "SXC" | "FLK" | "AIR"
+            | "AUR" | "AUL" | "AHD" | "ADW" | "ADR" | "ADL" | "ALD" | "ASD" | "ASC" | "MNE" => {
+                Self::Note
+            }
             _ => Self::None,
         };
 
@@ -70,25 +104,30 @@ impl ParserContext {
 
 /// Representation of a CHUNITHM chart. Ending tags are ignored right now
 /// as they do not seem to influence the functionality of the chart.
+///
+/// The text of the chart, such as the creator and note animations, is
+/// stored as `S`. See [`ChuniChartRef`] for a chart that borrows it from the
+/// input instead.
 #[derive(Default, Debug, PartialEq)]
-pub struct ChuniChart {
+pub struct ChuniChart<S = String> {
     /// The version of the chart format. `1.12.00` is the latest version as of
     /// current (CHUNITHM LUMINOUS).
-    pub version: String,
+    pub version: ChartVersion,
     /// The ID for the music track. Usually set to 0, as `Music.xml` now contains
     /// it.
     pub music: usize,
     /// The sequence ID of the chart. Usually set to 0.
     pub sequence_id: usize,
     /// The difficulty of the chart. Usually set to 00, as `Music.xml` now
-    /// contains it.
+    /// contains it. See [`ChuniChart::difficulty`] for its meaning.
     pub difficult: usize,
     /// The internal level of the chart. Usually set to 0.0, as `Music.xml` now
     /// contains it.
     pub level: f64,
     /// The creator of the chart file. This will display on the bottom left
-    /// corner of the song card, unless the difficulty is BASIC or ADVANCED.
-    pub creator: String,
+    /// corner of the song card, unless the difficulty is BASIC or ADVANCED,
+    /// as given by [`ChuniChart::displayed_creator`].
+    pub creator: S,
     /// The default BPM for the chart.
     pub bpm_def: BpmDef,
     /// The default metronome for the chart.
@@ -114,113 +153,234 @@ pub struct ChuniChart {
     /// All playfield speed designations in the chart.
     pub sfl: Vec<Sfl>,
     /// All notes in the chart.
-    pub notes: Vec<NoteType>,
+    pub notes: Vec<NoteType<S>>,
+    /// Lines that are not part of the chart, such as the `T_*` statistics
+    /// at the end of the file and tags that are not known yet, in the order
+    /// they were read in.
+    pub extra: Vec<Extra<S>>,
 }
 
+/// A [`ChuniChart`] borrowing all of its text from the `.c2s` file it was
+/// parsed from, made with [`ChuniChart::parse_ref`]. Parsing this way does
+/// not allocate for every note.
+pub type ChuniChartRef<'a> = ChuniChart<&'a str>;
+
 impl ChuniChart {
     /// Takes a [`String`] and parses it into a [`ChuniChart`].
     ///
     /// Returns a [`ParseError`] if any step during
     /// parsing fails.
     pub fn parse(input: String) -> Result<ChuniChart, ParseError> {
+        ChuniChart::parse_ref(&input).map(ChuniChart::from)
+    }
+
+    /// Parses `input` into a [`ChuniChartRef`] that borrows its text from
+    /// `input`.
+    ///
+    /// Returns a [`ParseError`] if any step during parsing fails.
+    pub fn parse_ref(input: &str) -> Result<ChuniChartRef<'_>, ParseError> {
         let mut chart = ChuniChart::default();
-        let mut current_section: ParserContext;
-
-        for line in input.lines() {
-            let line = line.trim();
-            if !line.trim().is_empty() {
-                current_section = ParserContext::get_section(line);
-                match current_section {
-                    ParserContext::Version => {
-                        let version = line.split('\t').nth(1).unwrap();
-                        chart.version = version.to_string();
-                    }
-
-                    ParserContext::Music => {
-                        let music = line.split('\t').nth(1).unwrap();
-                        chart.music = music.parse::<usize>().unwrap();
-                    }
-
-                    ParserContext::SequenceId => {
-                        let sequence_id = line.split('\t').nth(1).unwrap();
-                        chart.sequence_id = sequence_id.parse::<usize>().unwrap();
-                    }
-
-                    ParserContext::Difficult => {
-                        let difficult = line.split('\t').nth(1).unwrap();
-                        chart.difficult = difficult.parse::<usize>().unwrap();
-                    }
-
-                    ParserContext::Level => {
-                        let level = line.split('\t').nth(1).unwrap();
-                        chart.level = level.parse::<f64>().unwrap();
-                    }
-
-                    ParserContext::Creator => {
-                        let creator = line.split('\t').nth(1).unwrap();
-                        chart.creator = creator.to_string();
-                    }
-
-                    ParserContext::BpmDef => {
-                        let bpm_def = BpmDef::from_str(line).unwrap();
-                        chart.bpm_def = bpm_def;
-                    }
-
-                    ParserContext::MetDef => {
-                        let met_def = MetDef::from_str(line).unwrap();
-                        chart.met_def = met_def;
-                    }
-
-                    ParserContext::Resolution => {
-                        let resolution = line.split('\t').nth(1).unwrap();
-                        chart.resolution = resolution.parse::<usize>().unwrap();
-                    }
-
-                    ParserContext::ClkDef => {
-                        let clk_def = line.split('\t').nth(1).unwrap();
-                        chart.clk_def = clk_def.parse::<usize>().unwrap();
-                    }
-
-                    ParserContext::ProgJudgeBpm => {
-                        let progjudge_bpm = line.split('\t').nth(1).unwrap().trim();
-                        chart.progjudge_bpm = progjudge_bpm.parse::<f64>().unwrap();
-                    }
-
-                    ParserContext::ProgJudgeAer => {
-                        let progjudge_aer = line.split('\t').nth(1).unwrap().trim();
-                        chart.progjudge_aer = progjudge_aer.parse::<f64>().unwrap();
-                    }
-
-                    ParserContext::Tutorial => {
-                        let tutorial = line.split('\t').nth(1).unwrap().parse::<usize>().unwrap();
-                        chart.tutorial = tutorial != 0;
-                    }
-
-                    ParserContext::Bpm => {
-                        let bpm = Bpm::from_str(line).unwrap();
-                        chart.bpm.push(bpm);
-                    }
-
-                    ParserContext::Met => {
-                        let met = Met::from_str(line).unwrap();
-                        chart.met.push(met);
-                    }
-
-                    ParserContext::Sfl => {
-                        let sfl = Sfl::from_str(line).unwrap();
-                        chart.sfl.push(sfl);
-                    }
-
-                    ParserContext::Note => {
-                        let note = NoteType::from_str(line).unwrap();
-                        chart.notes.push(note);
-                    }
-
-                    ParserContext::None => continue,
-                }
-            }
+        let mut headers = 0;
+
+        for (_, line) in span::lines(input) {
+            chart.push(
+                C2sEvent::parse_versioned(line, chart.version)?,
+                &mut headers,
+            );
         }
 
         Ok(chart)
     }
+
+    /// Parses `input` like [`ChuniChart::parse`], along with a [`SourceMap`]
+    /// of the line every BPM, MET, SFL and note was read from.
+    pub fn parse_with_spans(input: String) -> Result<(ChuniChart, SourceMap), ParseError> {
+        let (chart, spans) = ChuniChart::parse_ref_with_spans(&input)?;
+        Ok((chart.into(), spans))
+    }
+
+    /// Parses `input` like [`ChuniChart::parse_ref`], along with a
+    /// [`SourceMap`] of the line every BPM, MET, SFL and note was read from.
+    pub fn parse_ref_with_spans(input: &str) -> Result<(ChuniChartRef<'_>, SourceMap), ParseError> {
+        let mut chart = ChuniChart::default();
+        let mut spans = SourceMap::default();
+        let mut headers = 0;
+
+        for (span, line) in span::lines(input) {
+            let event = C2sEvent::parse_versioned(line, chart.version)?;
+            spans.push(&event, span);
+            chart.push(event, &mut headers);
+        }
+
+        Ok((chart, spans))
+    }
+
+    /// Reads a chart from `reader` one line at a time, with [`C2sEvents`].
+    ///
+    /// Returns an [`io::Error`] if reading fails. Like [`ChuniChart::parse`],
+    /// it panics on lines that can't be parsed.
+    pub fn from_reader(reader: impl BufRead) -> io::Result<ChuniChart> {
+        let mut chart = ChuniChart::default();
+        let mut headers = 0;
+
+        for event in C2sEvents::new(reader) {
+            chart.push(event?, &mut headers);
+        }
+
+        Ok(chart)
+    }
+}
+
+impl<S> ChuniChart<S> {
+    /// Adds a single line of a `.c2s` file to the chart. `headers` counts the
+    /// header lines read so far, for placing extra lines among them.
+    fn push(&mut self, event: C2sEvent<S>, headers: &mut usize) {
+        if let C2sEvent::Header(_) = event {
+            *headers += 1;
+        }
+
+        match event {
+            C2sEvent::Header(header) => match header {
+                Header::Version(version) => self.version = version,
+                Header::Music(music) => self.music = music,
+                Header::SequenceId(sequence_id) => self.sequence_id = sequence_id,
+                Header::Difficult(difficult) => self.difficult = difficult,
+                Header::Level(level) => self.level = level,
+                Header::Creator(creator) => self.creator = creator,
+                Header::BpmDef(bpm_def) => self.bpm_def = bpm_def,
+                Header::MetDef(met_def) => self.met_def = met_def,
+                Header::Resolution(resolution) => self.resolution = resolution,
+                Header::ClkDef(clk_def) => self.clk_def = clk_def,
+                Header::ProgJudgeBpm(progjudge_bpm) => self.progjudge_bpm = progjudge_bpm,
+                Header::ProgJudgeAer(progjudge_aer) => self.progjudge_aer = progjudge_aer,
+                Header::Tutorial(tutorial) => self.tutorial = tutorial,
+            },
+            C2sEvent::Bpm(bpm) => self.bpm.push(bpm),
+            C2sEvent::Met(met) => self.met.push(met),
+            C2sEvent::Sfl(sfl) => self.sfl.push(sfl),
+            C2sEvent::Note(note) => self.notes.push(note),
+            C2sEvent::Statistic(statistic) => {
+                self.push_extra(ExtraLine::Statistic(statistic), *headers)
+            }
+            C2sEvent::Unknown(line) => self.push_extra(ExtraLine::Unknown(line), *headers),
+        }
+    }
+
+    /// Keeps an extra line, among the header lines if no BPM, MET, SFL or
+    /// note line was read yet.
+    fn push_extra(&mut self, line: ExtraLine<S>, headers: usize) {
+        let position = self.bpm.len() + self.met.len() + self.sfl.len() + self.notes.len();
+        let header = position == 0;
+        let position = if header { headers } else { position };
+
+        self.extra.push(Extra {
+            position,
+            header,
+            line,
+        });
+    }
+}
+
+impl From<ChuniChartRef<'_>> for ChuniChart {
+    /// Copies the borrowed text of the chart, leaving the input free to be
+    /// dropped.
+    fn from(chart: ChuniChartRef<'_>) -> ChuniChart {
+        ChuniChart {
+            version: chart.version,
+            music: chart.music,
+            sequence_id: chart.sequence_id,
+            difficult: chart.difficult,
+            level: chart.level,
+            creator: chart.creator.to_string(),
+            bpm_def: chart.bpm_def,
+            met_def: chart.met_def,
+            resolution: chart.resolution,
+            clk_def: chart.clk_def,
+            progjudge_bpm: chart.progjudge_bpm,
+            progjudge_aer: chart.progjudge_aer,
+            tutorial: chart.tutorial,
+            bpm: chart.bpm,
+            met: chart.met,
+            sfl: chart.sfl,
+            notes: chart.notes.into_iter().map(NoteType::into_owned).collect(),
+            extra: chart.extra.into_iter().map(Extra::into_owned).collect(),
+        }
+    }
+}
+
+impl<S: fmt::Display> fmt::Display for ChuniChart<S> {
+    /// Writes the chart back out in the `.c2s` format. Notes are written in
+    /// the order they are stored in.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = [
+            self.version.to_string(),
+            format!("MUSIC\t{}", self.music),
+            format!("SEQUENCEID\t{}", self.sequence_id),
+            format!("DIFFICULT\t{:02}", self.difficult),
+            format!("LEVEL\t{:.1}", self.level),
+            format!("CREATOR\t{}", self.creator),
+            self.bpm_def.to_string(),
+            self.met_def.to_string(),
+            format!("RESOLUTION\t{}", self.resolution),
+            format!("CLK_DEF\t{}", self.clk_def),
+            format!("PROGJUDGE_BPM\t{:.3}", self.progjudge_bpm),
+            format!("PROGJUDGE_AER\t{:>7.3}", self.progjudge_aer),
+            format!("TUTORIAL\t{}", self.tutorial as usize),
+        ];
+
+        // extra lines are written right before the line that came after them
+        // in the file, and the ones after every line of the chart make up
+        // the footer. the header lines are counted on their own.
+        let mut extra = self.extra.iter().filter(|x| x.header).peekable();
+        for (i, line) in header.iter().enumerate() {
+            while let Some(extra) = extra.next_if(|x| x.position <= i) {
+                writeln!(f, "{}", extra.line)?;
+            }
+            writeln!(f, "{}", line)?;
+        }
+        for extra in extra {
+            writeln!(f, "{}", extra.line)?;
+        }
+        writeln!(f)?;
+
+        let mut extra = self.extra.iter().filter(|x| !x.header).peekable();
+        let mut position = 0;
+        let mut write = |f: &mut fmt::Formatter<'_>, line: &dyn fmt::Display| {
+            while let Some(extra) = extra.next_if(|x| x.position <= position) {
+                writeln!(f, "{}", extra.line)?;
+            }
+
+            position += 1;
+            writeln!(f, "{}", line)
+        };
+
+        for bpm in &self.bpm {
+            write(f, bpm)?;
+        }
+
+        for met in &self.met {
+            write(f, met)?;
+        }
+
+        for sfl in &self.sfl {
+            write(f, sfl)?;
+        }
+
+        writeln!(f)?;
+
+        for note in &self.notes {
+            write(f, &note.display(self.version))?;
+        }
+
+        let footer = extra.collect::<Vec<_>>();
+        if !footer.is_empty() {
+            writeln!(f)?;
+            for extra in footer {
+                writeln!(f, "{}", extra.line)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
 }
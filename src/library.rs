@@ -0,0 +1,236 @@
+use std::{
+    error::Error,
+    fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+};
+
+use crate::{
+    music_xml::{FumenInfo, MusicInfo},
+    ChuniChart,
+};
+
+/// An error encountered while loading a single file of a [`Library`].
+#[derive(Debug)]
+pub enum LibraryError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file could be read, but parsing it failed. Contains the message
+    /// the parser bailed with.
+    Parse(String),
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io(e) => write!(f, "could not read file: {}", e),
+            LibraryError::Parse(e) => write!(f, "could not parse file: {}", e),
+        }
+    }
+}
+
+impl Error for LibraryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LibraryError::Io(e) => Some(e),
+            LibraryError::Parse(_) => None,
+        }
+    }
+}
+
+/// A single `.c2s` file in a song folder. The chart is only parsed the first
+/// time it is requested.
+#[derive(Debug)]
+pub struct ChartEntry {
+    /// The path to the `.c2s` file.
+    pub path: PathBuf,
+    /// The difficulty listed for this file in the song's `Music.xml`, if any.
+    pub fumen: Option<FumenInfo>,
+    music: Option<Arc<MusicInfo>>,
+    chart: OnceLock<Result<ChuniChart, LibraryError>>,
+}
+
+impl ChartEntry {
+    /// Returns the parsed chart, parsing it if this has not been done yet.
+    ///
+    /// If the song has a `Music.xml` listing this file, its metadata is
+    /// attached to the chart with [`MusicInfo::attach`].
+    ///
+    /// The parser bails on a broken chart by panicking. That panic is caught
+    /// and returned as a [`LibraryError::Parse`], but the panic hook still
+    /// sees it, so programs that want to keep it quiet have to set their own
+    /// hook. Builds with `panic = "abort"` abort instead.
+    pub fn chart(&self) -> Result<&ChuniChart, &LibraryError> {
+        self.chart
+            .get_or_init(|| {
+                let mut chart = load(&self.path, ChuniChart::parse)?;
+                if let Some(music) = &self.music {
+                    music.attach(&mut chart, &self.path);
+                }
+
+                Ok(chart)
+            })
+            .as_ref()
+    }
+
+    /// Whether or not the chart has already been parsed.
+    pub fn is_loaded(&self) -> bool {
+        self.chart.get().is_some()
+    }
+}
+
+/// A single `musicXXXX` folder.
+#[derive(Debug)]
+pub struct Song {
+    /// The path to the song folder.
+    pub path: PathBuf,
+    /// The parsed `Music.xml` of the song, shared with its charts.
+    pub music: Result<Arc<MusicInfo>, LibraryError>,
+    /// All `.c2s` files in the song folder, sorted by file name.
+    pub charts: Vec<ChartEntry>,
+}
+
+/// An index of every song and chart in a game data directory, laid out as
+/// `music/musicXXXX/`, with a `Music.xml` and several `.c2s` files in each
+/// song folder.
+#[derive(Debug)]
+pub struct Library {
+    /// All song folders, sorted by folder name.
+    pub songs: Vec<Song>,
+}
+
+impl Library {
+    /// Walks the `music` folder inside `root` and builds an index of every
+    /// song. `Music.xml` files are parsed right away, while charts are only
+    /// parsed when requested, either one by one with [`ChartEntry::chart`] or
+    /// all at once with [`Library::load`].
+    ///
+    /// Returns an [`io::Error`] if the `music` folder itself cannot be read.
+    /// Errors in individual songs are stored in the song instead.
+    pub fn scan(root: impl AsRef<Path>) -> io::Result<Library> {
+        let mut dirs = fs::read_dir(root.as_ref().join("music"))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        dirs.sort();
+
+        let songs = dirs
+            .into_iter()
+            .map(|path| {
+                let music = load(&path.join("Music.xml"), |s| s.parse::<MusicInfo>()).map(Arc::new);
+
+                let mut files = fs::read_dir(&path)
+                    .map(|entries| {
+                        entries
+                            .filter_map(|entry| entry.ok())
+                            .map(|entry| entry.path())
+                            .filter(|path| path.extension().is_some_and(|x| x == "c2s"))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                files.sort();
+
+                let charts = files
+                    .into_iter()
+                    .map(|file| ChartEntry {
+                        fumen: music
+                            .as_ref()
+                            .ok()
+                            .and_then(|music| music.fumen(&file))
+                            .cloned(),
+                        music: music.as_ref().ok().map(Arc::clone),
+                        path: file,
+                        chart: OnceLock::new(),
+                    })
+                    .collect();
+
+                Song {
+                    path,
+                    music,
+                    charts,
+                }
+            })
+            .collect();
+
+        Ok(Library { songs })
+    }
+
+    /// Returns every chart entry in the library.
+    pub fn charts(&self) -> impl Iterator<Item = &ChartEntry> {
+        self.songs.iter().flat_map(|song| song.charts.iter())
+    }
+
+    /// Parses every chart that has not been parsed yet, spread over all
+    /// available threads. See [`ChartEntry::chart`] for how broken charts are
+    /// handled.
+    pub fn load(&self) {
+        let entries = self
+            .charts()
+            .filter(|entry| !entry.is_loaded())
+            .collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+        let threads = thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1)
+            .min(entries.len());
+
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    while let Some(entry) = entries.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let _ = entry.chart();
+                    }
+                });
+            }
+        });
+    }
+
+    /// Returns every error encountered so far, along with the path of the
+    /// file that caused it.
+    pub fn errors(&self) -> Vec<(&Path, &LibraryError)> {
+        let mut errors = vec![];
+
+        for song in &self.songs {
+            if let Err(e) = &song.music {
+                errors.push((song.path.as_path(), e));
+            }
+
+            for entry in &song.charts {
+                if let Some(Err(e)) = entry.chart.get() {
+                    errors.push((entry.path.as_path(), e));
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+/// Reads a file and parses it, turning any panic in the parser into a
+/// [`LibraryError::Parse`].
+fn load<T, E: fmt::Debug>(
+    path: &Path,
+    parse: impl FnOnce(String) -> Result<T, E>,
+) -> Result<T, LibraryError> {
+    let input = fs::read_to_string(path).map_err(LibraryError::Io)?;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| parse(input)));
+
+    match result {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(e)) => Err(LibraryError::Parse(format!("{:?}", e))),
+        Err(payload) => Err(LibraryError::Parse(
+            payload
+                .downcast_ref::<&str>()
+                .map(|x| x.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string()),
+        )),
+    }
+}
@@ -0,0 +1,314 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    json::{Json, ToJson},
+    note::NoteType,
+    ChuniChart,
+};
+
+/// How far apart two BPMs can be while still being the same one, as
+/// `BPM_DEF` only keeps 3 decimals.
+const BPM_TOLERANCE: f64 = 0.001;
+
+/// How severe a [`Lint`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The chart can be loaded, but probably does not do what the charter
+    /// intended.
+    Warning,
+    /// The chart is broken, and will most likely not load in game.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// The line of a chart a [`Lint`] was found on, which can be looked up with
+/// [`SourceMap::get`][crate::span::SourceMap::get].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// A header line, by its tag, e.g. `BPM_DEF`.
+    Header(&'static str),
+    /// The BPM designation at this index of `chart.bpm`.
+    Bpm(usize),
+    /// The MET designation at this index of `chart.met`.
+    Met(usize),
+    /// The SFL designation at this index of `chart.sfl`.
+    Sfl(usize),
+    /// The note at this index of `chart.notes`.
+    Note(usize),
+}
+
+/// A single problem found in a chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub severity: Severity,
+    /// The line the problem was found on.
+    pub source: Source,
+    /// The measure the problem was found in.
+    pub measure: usize,
+    /// The offset the problem was found at.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (measure {}, offset {})",
+            self.severity, self.message, self.measure, self.offset
+        )
+    }
+}
+
+impl ToJson for Lint {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("severity", self.severity.to_string().into()),
+            ("measure", self.measure.into()),
+            ("offset", self.offset.into()),
+            ("message", self.message.as_str().into()),
+        ])
+    }
+}
+
+impl ChuniChart {
+    /// Checks the chart for common mistakes, such as notes outside of the
+    /// playfield, or a `BPM_DEF` that does not match the BPM designations.
+    ///
+    /// Lints are returned in the order they appear in the chart.
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = vec![];
+        let mut lint = |severity, source, message: String| {
+            let (measure, offset) = self.position(source);
+            lints.push(Lint {
+                severity,
+                source,
+                measure,
+                offset,
+                message,
+            })
+        };
+
+        match self.bpm.first() {
+            Some(bpm) if bpm.beginning_measure == 0 && bpm.offset == 0 => {
+                if (bpm.bpm - self.bpm_def.starting_bpm).abs() > BPM_TOLERANCE {
+                    lint(
+                        Severity::Warning,
+                        Source::Header("BPM_DEF"),
+                        format!(
+                            "BPM_DEF starting BPM {:.3} does not match the first BPM {:.3}",
+                            self.bpm_def.starting_bpm, bpm.bpm
+                        ),
+                    );
+                }
+            }
+            // the first BPM is the misplaced line, if there is one at all.
+            first => lint(
+                Severity::Error,
+                first.map_or(Source::Header("BPM_DEF"), |_| Source::Bpm(0)),
+                "no BPM designated at the start of the chart".to_string(),
+            ),
+        }
+
+        let highest = self.bpm.iter().map(|x| x.bpm).fold(f64::MIN, f64::max);
+        let lowest = self.bpm.iter().map(|x| x.bpm).fold(f64::MAX, f64::min);
+        if !self.bpm.is_empty()
+            && ((highest - self.bpm_def.highest_bpm).abs() > BPM_TOLERANCE
+                || (lowest - self.bpm_def.lowest_bpm).abs() > BPM_TOLERANCE)
+        {
+            lint(
+                Severity::Warning,
+                Source::Header("BPM_DEF"),
+                format!(
+                    "BPM_DEF range {:.3}-{:.3} does not match the designated range {:.3}-{:.3}",
+                    self.bpm_def.lowest_bpm, self.bpm_def.highest_bpm, lowest, highest
+                ),
+            );
+        }
+
+        for (i, bpm) in self.bpm.iter().enumerate() {
+            if bpm.bpm <= 0.0 {
+                lint(
+                    Severity::Error,
+                    Source::Bpm(i),
+                    format!("BPM {:.3} is not positive", bpm.bpm),
+                );
+            }
+        }
+
+        let offsets = self
+            .bpm
+            .iter()
+            .enumerate()
+            .map(|(i, x)| ("BPM", Source::Bpm(i), x.offset))
+            .chain(
+                self.met
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| ("MET", Source::Met(i), x.offset)),
+            )
+            .chain(
+                self.sfl
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| ("SFL", Source::Sfl(i), x.offset)),
+            )
+            .chain(
+                self.notes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| (x.tag(), Source::Note(i), x.offset())),
+            );
+
+        for (tag, source, offset) in offsets {
+            if offset >= self.resolution {
+                lint(
+                    Severity::Error,
+                    source,
+                    format!(
+                        "{} offset is not smaller than the resolution {}",
+                        tag, self.resolution
+                    ),
+                );
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let anchors = self
+            .notes
+            .iter()
+            .flat_map(|note| anchors(note, self.resolution))
+            .collect::<HashSet<_>>();
+
+        for (i, note) in self.notes.iter().enumerate() {
+            let (source, tag) = (Source::Note(i), note.tag());
+
+            if note.width() == 0 || note.cell() + note.width() > 16 {
+                lint(
+                    Severity::Error,
+                    source,
+                    format!(
+                        "{} at cell {} with width {} is outside of the playfield",
+                        tag,
+                        note.cell(),
+                        note.width()
+                    ),
+                );
+            }
+
+            if let Some((end_cell, end_width)) = note.end() {
+                if end_width == 0 || end_cell + end_width > 16 {
+                    lint(
+                        Severity::Error,
+                        source,
+                        format!(
+                            "{} ends at cell {} with width {}, outside of the playfield",
+                            tag, end_cell, end_width
+                        ),
+                    );
+                }
+            }
+
+            if note.duration() == Some(0) {
+                lint(
+                    Severity::Warning,
+                    source,
+                    format!("{} has no duration", tag),
+                );
+            }
+
+            if !seen.insert(note.to_string()) {
+                lint(
+                    Severity::Warning,
+                    source,
+                    format!("duplicate {} at cell {}", tag, note.cell()),
+                );
+            }
+
+            if let Some(target) = target(note) {
+                let tick = note.tick(self.resolution);
+                let found = (note.cell()..note.cell() + note.width())
+                    .any(|cell| anchors.contains(&(family(target), tick, cell)));
+
+                if !found {
+                    lint(
+                        Severity::Warning,
+                        source,
+                        format!(
+                            "{} has no {} to leech from at cell {}",
+                            tag,
+                            target,
+                            note.cell()
+                        ),
+                    );
+                }
+            }
+        }
+
+        lints
+    }
+
+    /// The measure and offset of the line `source` points to, or the start
+    /// of the chart for headers.
+    fn position(&self, source: Source) -> (usize, usize) {
+        match source {
+            Source::Header(_) => (0, 0),
+            Source::Bpm(i) => (self.bpm[i].beginning_measure, self.bpm[i].offset),
+            Source::Met(i) => (self.met[i].beginning_measure, self.met[i].offset),
+            Source::Sfl(i) => (self.sfl[i].beginning_measure, self.sfl[i].offset),
+            Source::Note(i) => (self.notes[i].measure(), self.notes[i].offset()),
+        }
+    }
+}
+
+/// Groups note tags that an air note can refer to interchangeably, such as
+/// a Hold and a HoldWithExTapHead.
+fn family(tag: &str) -> &str {
+    match tag {
+        "HXD" => "HLD",
+        "SLC" | "SXD" | "SXC" => "SLD",
+        "ASC" => "ASD",
+        tag => tag,
+    }
+}
+
+/// The tag of the note an air note leeches from.
+fn target(note: &NoteType) -> Option<&str> {
+    match note {
+        NoteType::Air(note)
+        | NoteType::AirUpRight(note)
+        | NoteType::AirUpLeft(note)
+        | NoteType::AirDown(note)
+        | NoteType::AirDownRight(note)
+        | NoteType::AirDownLeft(note) => Some(&note.target_note),
+        NoteType::AirHold(note) => Some(&note.target_note),
+        NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => Some(&note.target_note),
+        _ => None,
+    }
+}
+
+/// Every position an air note could be placed on top of this note, which is
+/// the start of the note, and the end of the note if it has a length.
+fn anchors(note: &NoteType, resolution: usize) -> Vec<(&'static str, usize, usize)> {
+    let tag = family(note.tag());
+    let tick = note.tick(resolution);
+
+    let mut anchors = (note.cell()..note.cell() + note.width())
+        .map(|cell| (tag, tick, cell))
+        .collect::<Vec<_>>();
+
+    if let Some(duration) = note.duration() {
+        let (cell, width) = note.end().unwrap_or((note.cell(), note.width()));
+        anchors.extend((cell..cell + width).map(|cell| (tag, tick + duration, cell)));
+    }
+
+    anchors
+}
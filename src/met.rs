@@ -1,12 +1,59 @@
-use std::{str::FromStr, string::ParseError};
+use std::{fmt, str::FromStr, string::ParseError};
 
-// TODO: figure out the order of the first_value and second_value. unsure
-// whether they're the same as MET where the second_value comes first or
-// not.
-#[derive(Debug, Default, PartialEq)]
+use crate::ChuniChart;
+
+/// A time signature, such as 7/4. Time signatures only change where bar
+/// lines are drawn and how beats are grouped, as measures in `.c2s` files
+/// are always `resolution` ticks long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeSignature {
+    /// The amount of beats in a bar.
+    pub numerator: usize,
+    /// The note value of a beat, e.g. 4 for quarter notes.
+    pub denominator: usize,
+}
+
+impl TimeSignature {
+    /// The 4/4 time signature, which every chart starts with.
+    pub const COMMON: TimeSignature = TimeSignature {
+        numerator: 4,
+        denominator: 4,
+    };
+
+    /// The length of a beat in ticks. `resolution` is the length of a whole
+    /// note, so a quarter note is 96 ticks long at the default resolution of
+    /// 384.
+    pub fn beat_length(&self, resolution: usize) -> usize {
+        resolution / self.denominator.max(1)
+    }
+
+    /// The length of a bar in ticks, e.g. 672 ticks for 7/4 at the default
+    /// resolution of 384.
+    pub fn bar_length(&self, resolution: usize) -> usize {
+        resolution * self.numerator / self.denominator.max(1)
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature::COMMON
+    }
+}
+
+impl fmt::Display for TimeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// Default time signature for the chart, written as `MET_DEF 4 4`.
+///
+/// Every known chart uses 4/4 here, so the order of the two values can't be
+/// told apart. They are read in the same order as in [`Met`], with the
+/// denominator first.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct MetDef {
-    pub first_value: usize,
-    pub second_value: usize,
+    pub signature: TimeSignature,
 }
 
 impl FromStr for MetDef {
@@ -16,22 +63,31 @@ impl FromStr for MetDef {
         let (_, data) = s.trim().split_once('\t').unwrap();
         let mut data = data.trim().split('\t');
 
-        let first_value = data.next().unwrap().parse::<usize>().unwrap();
-        let second_value = data.next().unwrap().parse::<usize>().unwrap();
+        let denominator = data.next().unwrap().parse::<usize>().unwrap();
+        let numerator = data.next().unwrap().parse::<usize>().unwrap();
 
         Ok(MetDef {
-            first_value,
-            second_value,
+            signature: TimeSignature {
+                numerator,
+                denominator,
+            },
         })
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+/// Time signature designation for a specified measure in the chart, written
+/// as `MET measure offset denominator numerator`.
+///
+/// The denominator comes first: `MET 45 192 4 7` switches to 7/4, and the
+/// next designation, `MET 59 192 4 4`, is exactly 8 bars of 672 ticks later.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Met {
+    /// The starting measure of the designation.
     pub beginning_measure: usize,
+    /// The offset of the designation. This is calculated in the same method
+    /// as described in [`ChuniChart`]'s `resolution` field.
     pub offset: usize,
-    pub second_value: usize,
-    pub first_value: usize,
+    pub signature: TimeSignature,
 }
 
 impl FromStr for Met {
@@ -43,14 +99,74 @@ impl FromStr for Met {
 
         let beginning_measure = data.next().unwrap().parse::<usize>().unwrap();
         let offset = data.next().unwrap().parse::<usize>().unwrap();
-        let second_value = data.next().unwrap().parse::<usize>().unwrap();
-        let first_value = data.next().unwrap().parse::<usize>().unwrap();
+        let denominator = data.next().unwrap().parse::<usize>().unwrap();
+        let numerator = data.next().unwrap().parse::<usize>().unwrap();
 
         Ok(Met {
             beginning_measure,
             offset,
-            second_value,
-            first_value,
+            signature: TimeSignature {
+                numerator,
+                denominator,
+            },
         })
     }
 }
+
+impl fmt::Display for MetDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MET_DEF\t{}\t{}",
+            self.signature.denominator, self.signature.numerator
+        )
+    }
+}
+
+impl fmt::Display for Met {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MET\t{}\t{}\t{}\t{}",
+            self.beginning_measure,
+            self.offset,
+            self.signature.denominator,
+            self.signature.numerator
+        )
+    }
+}
+
+impl ChuniChart {
+    /// Every time signature of the chart with the tick it starts at, sorted
+    /// by position. The one from `MET_DEF` is used until the first
+    /// designation, if the chart does not designate one at its start.
+    pub fn time_signatures(&self) -> Vec<(usize, TimeSignature)> {
+        let mut mets = self
+            .met
+            .iter()
+            .map(|x| {
+                (
+                    x.beginning_measure * self.resolution + x.offset,
+                    x.signature,
+                )
+            })
+            .collect::<Vec<_>>();
+        mets.sort_by_key(|(tick, _)| *tick);
+
+        if mets.first().is_none_or(|(tick, _)| *tick != 0) {
+            mets.insert(0, (0, self.met_def.signature));
+        }
+
+        mets
+    }
+
+    /// The time signature in effect at the given position.
+    pub fn time_signature(&self, tick: usize) -> TimeSignature {
+        self.time_signatures()
+            .into_iter()
+            .rev()
+            .find(|(start, _)| *start <= tick)
+            .map(|(_, signature)| signature)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,314 @@
+use std::{path::Path, str::FromStr, string::ParseError};
+
+use crate::{difficulty::Difficulty, ChuniChart};
+
+/// Information about a single difficulty of a song, as listed in the
+/// `<fumens>` section of `Music.xml`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FumenInfo {
+    /// The numerical ID of the difficulty. 0 is BASIC, 1 is ADVANCED, 2 is
+    /// EXPERT, 3 is MASTER, 4 is ULTIMA and 5 is WORLD'S END.
+    pub difficulty: usize,
+    /// The name of the difficulty, e.g. `MASTER`.
+    pub difficulty_name: String,
+    /// Whether or not the difficulty is playable. Disabled difficulties
+    /// usually do not have a chart file shipped.
+    pub enabled: bool,
+    /// The path to the `.c2s` file, relative to the `Music.xml` file.
+    pub file: String,
+    /// The integer part of the internal level.
+    pub level: usize,
+    /// The decimal part of the internal level, in hundredths. A chart with
+    /// `level` 13 and `level_decimal` 70 has an internal level of 13.7.
+    pub level_decimal: usize,
+}
+
+impl FumenInfo {
+    /// The internal level of the difficulty, combining `level` and
+    /// `level_decimal`.
+    pub fn internal_level(&self) -> f64 {
+        self.level as f64 + self.level_decimal as f64 / 100.0
+    }
+
+    /// The `difficulty` ID as a [`Difficulty`].
+    pub fn kind(&self) -> Option<Difficulty> {
+        Difficulty::from_id(self.difficulty)
+    }
+}
+
+/// Representation of a CHUNITHM `Music.xml` file, which holds the metadata
+/// for a song and all of its difficulties.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MusicInfo {
+    /// The ID for the music track. Charts reference this with their `MUSIC`
+    /// field, although it is usually set to 0 in the chart itself.
+    pub id: usize,
+    /// The title of the song.
+    pub title: String,
+    /// The string the song is sorted by in the song select screen.
+    pub sort_name: String,
+    /// The artist of the song.
+    pub artist: String,
+    /// All genres the song belongs to.
+    pub genres: Vec<String>,
+    /// The path to the jacket image, relative to the `Music.xml` file.
+    pub jacket: String,
+    /// The ID of the cue file containing the song audio.
+    pub cue_id: usize,
+    /// The name of the cue file containing the song audio.
+    pub cue_name: String,
+    /// All difficulties listed for the song.
+    pub fumens: Vec<FumenInfo>,
+}
+
+impl FromStr for MusicInfo {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let root = Element::parse(s);
+
+        let genres = root
+            .find(&["genreNames", "list"])
+            .map(|list| {
+                list.children
+                    .iter()
+                    .filter_map(|genre| genre.text_of(&["str"]))
+                    .map(|x| x.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let fumens = root
+            .find(&["fumens"])
+            .map(|fumens| {
+                fumens
+                    .children
+                    .iter()
+                    .map(|fumen| FumenInfo {
+                        difficulty: fumen.number_of(&["type", "id"]),
+                        difficulty_name: fumen
+                            .text_of(&["type", "data"])
+                            .unwrap_or_default()
+                            .to_string(),
+                        enabled: fumen.text_of(&["enable"]) == Some("true"),
+                        file: fumen
+                            .text_of(&["file", "path"])
+                            .unwrap_or_default()
+                            .to_string(),
+                        level: fumen.number_of(&["level"]),
+                        level_decimal: fumen.number_of(&["levelDecimal"]),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(MusicInfo {
+            id: root.number_of(&["name", "id"]),
+            title: root
+                .text_of(&["name", "str"])
+                .unwrap_or_default()
+                .to_string(),
+            sort_name: root.text_of(&["sortName"]).unwrap_or_default().to_string(),
+            artist: root
+                .text_of(&["artistName", "str"])
+                .unwrap_or_default()
+                .to_string(),
+            genres,
+            // the typo is present in the game files.
+            jacket: root
+                .text_of(&["jaketFile", "path"])
+                .unwrap_or_default()
+                .to_string(),
+            cue_id: root.number_of(&["cueFileName", "id"]),
+            cue_name: root
+                .text_of(&["cueFileName", "str"])
+                .unwrap_or_default()
+                .to_string(),
+            fumens,
+        })
+    }
+}
+
+impl MusicInfo {
+    /// Returns the difficulty whose chart file has the same file name as
+    /// `file`.
+    pub fn fumen(&self, file: impl AsRef<Path>) -> Option<&FumenInfo> {
+        let file_name = file.as_ref().file_name()?;
+
+        self.fumens
+            .iter()
+            .find(|fumen| Path::new(&fumen.file).file_name() == Some(file_name))
+    }
+
+    /// Fills in the `music`, `difficult` and `level` fields of a chart that
+    /// was loaded from `file`, as these are usually left empty in the chart
+    /// itself.
+    ///
+    /// Returns the matching difficulty, or `None` if `file` is not listed in
+    /// this `Music.xml`, in which case the chart is left untouched.
+    pub fn attach(&self, chart: &mut ChuniChart, file: impl AsRef<Path>) -> Option<&FumenInfo> {
+        let fumen = self.fumen(file)?;
+
+        chart.music = self.id;
+        chart.difficult = fumen.difficulty;
+        chart.level = fumen.internal_level();
+
+        Some(fumen)
+    }
+}
+
+/// A minimal XML element tree, covering just enough of the format to read
+/// `Music.xml` files. Attributes are skipped, as the game does not store any
+/// data in them.
+///
+/// Malformed documents do not fail to parse: unterminated markup is dropped,
+/// stray closing tags are ignored and unclosed elements are closed at the end
+/// of the input, so missing fields simply read as empty.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    /// Parses a document and returns its root element.
+    fn parse(input: &str) -> Element {
+        let mut stack = vec![Element::default()];
+        let mut rest = input;
+
+        while let Some(start) = rest.find('<') {
+            let text = &rest[..start];
+            if !text.trim().is_empty() {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .text
+                    .push_str(&unescape(text.trim()));
+            }
+
+            rest = &rest[start..];
+
+            // comments and CDATA sections may contain a bare `>`, so they have
+            // to be skipped up to their own terminator.
+            let terminator = if rest.starts_with("<!--") {
+                "-->"
+            } else if rest.starts_with("<![CDATA[") {
+                "]]>"
+            } else {
+                ">"
+            };
+            let Some(end) = rest.find(terminator) else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + terminator.len()..];
+
+            if let Some(data) = tag.strip_prefix("![CDATA[") {
+                stack.last_mut().unwrap().text.push_str(data.trim());
+                continue;
+            }
+
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+
+            if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                // a closing tag also closes anything left open inside it.
+                if let Some(depth) = stack.iter().skip(1).rposition(|x| x.name == name) {
+                    while stack.len() > depth + 1 {
+                        let element = stack.pop().unwrap();
+                        stack.last_mut().unwrap().children.push(element);
+                    }
+                }
+                continue;
+            }
+
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let Some(name) = tag.split_whitespace().next() else {
+                continue;
+            };
+            let element = Element {
+                name: name.to_string(),
+                ..Default::default()
+            };
+
+            if self_closing {
+                stack.last_mut().unwrap().children.push(element);
+            } else {
+                stack.push(element);
+            }
+        }
+
+        while stack.len() > 1 {
+            let element = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(element);
+        }
+
+        let mut document = stack.pop().unwrap();
+        document.children.pop().unwrap_or_default()
+    }
+
+    fn find(&self, path: &[&str]) -> Option<&Element> {
+        path.iter().try_fold(self, |element, name| {
+            element.children.iter().find(|child| child.name == *name)
+        })
+    }
+
+    fn text_of(&self, path: &[&str]) -> Option<&str> {
+        self.find(path).map(|element| element.text.as_str())
+    }
+
+    /// Reads the text at `path` as a number, or 0 if it is missing or not a
+    /// number.
+    fn number_of(&self, path: &[&str]) -> usize {
+        self.text_of(path)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|x| u32::from_str_radix(x, 16))
+                .or_else(|| entity.strip_prefix('#').map(|x| x.parse::<u32>()))
+                .and_then(|x| x.ok())
+                .and_then(char::from_u32),
+        };
+
+        match c {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
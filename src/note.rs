@@ -1,11 +1,13 @@
 #![allow(clippy::tabs_in_doc_comments)]
-use std::{str::FromStr, string::ParseError};
+use std::{fmt, str::FromStr, string::ParseError};
+
+use crate::version::ChartVersion;
 
 /// Tap notes are the most basic notes that can be charted. They simply require
 /// the player to hit the cell that the note occupies at the required time.
 ///
 /// Tap notes also represent the universal note schema.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Tap {
     /// The specific measure the note will be placed in.
     pub measure: usize,
@@ -24,8 +26,8 @@ pub struct Tap {
 
 /// ExTaps are the same as Taps, but they will always be judged as a CRITICAL
 /// JUSTICE when hit.
-#[derive(Debug, Default, PartialEq)]
-pub struct ExTap {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExTap<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -47,13 +49,13 @@ pub struct ExTap {
     /// - RS: Horizontal effects from left to right.
     /// - LC: Effects rotate counter-clockwise.
     /// - RC: Effects rotate clockwise.
-    pub animation: String,
+    pub animation: S,
 }
 
 /// Hold notes are similar to tap notes, but the player must keep the designated
 /// cell pressed over a continuous amount of time.
-#[derive(Debug, Default, PartialEq)]
-pub struct Hold {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hold<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -81,15 +83,15 @@ pub struct Hold {
     /// - RC: Effects rotate clockwise.
     ///
     /// Should always be represented with `None` if it is a normal Hold.
-    pub animation: Option<String>,
+    pub animation: Option<S>,
 }
 
 /// Introduced in LUMINOUS, these Holds have an ExTap as their head, instead of
 /// being notated as two notes: a Hold with an ExTap on top of it.
-pub type HoldWithExTapHead = Hold;
+pub type HoldWithExTapHead<S = String> = Hold<S>;
 
-#[derive(Debug, Default, PartialEq)]
-pub struct Slide {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Slide<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -121,15 +123,18 @@ pub struct Slide {
     /// - RC: Effects rotate clockwise.
     ///
     /// Should always be represented with `None` if it is a normal Slide or SlideControlPoint.
-    pub animation: Option<String>,
+    pub animation: Option<S>,
+    /// The field right before the animation. Always has a value of `SLD`, for
+    /// every kind of slide.
+    pub unknown: S,
 }
 
-pub type SlideControlPoint = Slide;
-pub type SlideWithExTapHead = Slide;
-pub type SlideControlPointWithExTapHead = Slide;
+pub type SlideControlPoint<S = String> = Slide<S>;
+pub type SlideWithExTapHead<S = String> = Slide<S>;
+pub type SlideControlPointWithExTapHead<S = String> = Slide<S>;
 
-#[derive(Debug, Default, PartialEq)]
-pub struct Flick {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Flick<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -144,11 +149,11 @@ pub struct Flick {
     /// specified.
     pub width: usize,
     /// Always has a value of `L`. This is not the direction of the flick note, as they can be hit from either direction.
-    pub unknown: String,
+    pub unknown: S,
 }
 
-#[derive(Debug, Default, PartialEq)]
-pub struct Air {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Air<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -163,17 +168,20 @@ pub struct Air {
     /// specified.
     pub width: usize,
     /// Which note the Air note "leeches" off of.
-    pub target_note: String,
+    pub target_note: S,
+    /// The color of the note. Usually `DEF`, see [`AirColor`] for the
+    /// others.
+    pub color: S,
 }
 
-pub type AirUpRight = Air;
-pub type AirUpLeft = Air;
-pub type AirDown = Air;
-pub type AirDownRight = Air;
-pub type AirDownLeft = Air;
+pub type AirUpRight<S = String> = Air<S>;
+pub type AirUpLeft<S = String> = Air<S>;
+pub type AirDown<S = String> = Air<S>;
+pub type AirDownRight<S = String> = Air<S>;
+pub type AirDownLeft<S = String> = Air<S>;
 
-#[derive(Debug, Default, PartialEq)]
-pub struct AirHold {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AirHold<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -187,12 +195,15 @@ pub struct AirHold {
     /// minimum value is 1, which means that the note only occupies the column
     /// specified.
     pub width: usize,
-    pub target_note: String,
+    pub target_note: S,
     pub duration: usize,
+    /// The color of the note. Usually `DEF`, see [`AirColor`] for the
+    /// others.
+    pub color: S,
 }
 
-#[derive(Debug, Default, PartialEq)]
-pub struct AirTrace {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AirTrace<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -212,13 +223,69 @@ pub struct AirTrace {
     pub end_cell: usize,
     pub end_width: usize,
     pub target_height: f64,
-    pub color: String,
+    pub color: S,
+}
+
+pub type AirCrush<S = String> = AirTrace<S>;
+
+/// The color of an AirCrush or AirSlide, parsed from the three letter code
+/// stored in their `color` field.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AirColor {
+    /// `DEF`: the default color of the note type.
+    #[default]
+    Default,
+    /// `NON`: the note is not drawn.
+    None,
+    Gray,
+    Red,
+    Orange,
+    Yellow,
+    Aqua,
+    Purple,
+    Pink,
+    Cyan,
+    Black,
+    Violet,
+    Lime,
+    Blue,
+}
+
+impl FromStr for AirColor {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color = match s {
+            "DEF" => AirColor::Default,
+            "NON" => AirColor::None,
+            "GRY" => AirColor::Gray,
+            "RED" => AirColor::Red,
+            "ORN" => AirColor::Orange,
+            "YEL" => AirColor::Yellow,
+            "AQA" => AirColor::Aqua,
+            "PPL" => AirColor::Purple,
+            "PNK" => AirColor::Pink,
+            "CYN" => AirColor::Cyan,
+            "BLK" => AirColor::Black,
+            "VLT" => AirColor::Violet,
+            "LIM" => AirColor::Lime,
+            "BLU" => AirColor::Blue,
+            _ => unreachable!("invalid air color found. bailing."),
+        };
+
+        Ok(color)
+    }
 }
 
-pub type AirCrush = AirTrace;
+impl<S: AsRef<str>> AirTrace<S> {
+    /// The color of the note as an [`AirColor`].
+    pub fn air_color(&self) -> AirColor {
+        AirColor::from_str(self.color.as_ref()).unwrap()
+    }
+}
 
-#[derive(Debug, Default, PartialEq)]
-pub struct AirSlide {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AirSlide<S = String> {
     /// The specific measure the note will be placed in.
     pub measure: usize,
     /// The offset of the note from the start of the specified measure.
@@ -232,43 +299,50 @@ pub struct AirSlide {
     /// minimum value is 1, which means that the note only occupies the column
     /// specified.
     pub width: usize,
-    pub target_note: String,
+    pub target_note: S,
     pub starting_height: f64,
     pub duration: usize,
     pub end_cell: usize,
     pub end_width: usize,
     pub target_height: f64,
-    pub color: String,
+    pub color: S,
 }
 
-pub type AirSlideControlPoint = AirSlide;
+pub type AirSlideControlPoint<S = String> = AirSlide<S>;
+
+impl<S: AsRef<str>> AirSlide<S> {
+    /// The color of the note as an [`AirColor`].
+    pub fn air_color(&self) -> AirColor {
+        AirColor::from_str(self.color.as_ref()).unwrap()
+    }
+}
 
 /// A mine note involves the player not touching the cell that the mine is
 /// placed on. Touching the cell will result in the player losing score,
 /// and possibly failing the track.
 pub type Mine = Tap;
 
-#[derive(Debug, PartialEq)]
-pub enum NoteType {
+#[derive(Debug, Clone, PartialEq)]
+pub enum NoteType<S = String> {
     Tap(Tap),
-    ExTap(ExTap),
-    Hold(Hold),
-    HoldWithExTapHead(HoldWithExTapHead),
-    Slide(Slide),
-    SlideControlPoint(SlideControlPoint),
-    SlideWithExTapHead(SlideWithExTapHead),
-    SlideControlPointWithExTapHead(SlideControlPointWithExTapHead),
-    Flick(Flick),
-    Air(Air),
-    AirUpRight(AirUpRight),
-    AirUpLeft(AirUpLeft),
-    AirHold(AirHold),
-    AirDown(AirDown),
-    AirDownRight(AirDownRight),
-    AirDownLeft(AirDownLeft),
-    AirCrush(AirCrush),
-    AirSlide(AirSlide),
-    AirSlideControlPoint(AirSlideControlPoint),
+    ExTap(ExTap<S>),
+    Hold(Hold<S>),
+    HoldWithExTapHead(HoldWithExTapHead<S>),
+    Slide(Slide<S>),
+    SlideControlPoint(SlideControlPoint<S>),
+    SlideWithExTapHead(SlideWithExTapHead<S>),
+    SlideControlPointWithExTapHead(SlideControlPointWithExTapHead<S>),
+    Flick(Flick<S>),
+    Air(Air<S>),
+    AirUpRight(AirUpRight<S>),
+    AirUpLeft(AirUpLeft<S>),
+    AirHold(AirHold<S>),
+    AirDown(AirDown<S>),
+    AirDownRight(AirDownRight<S>),
+    AirDownLeft(AirDownLeft<S>),
+    AirCrush(AirCrush<S>),
+    AirSlide(AirSlide<S>),
+    AirSlideControlPoint(AirSlideControlPoint<S>),
     Mine(Mine),
 }
 
@@ -276,6 +350,28 @@ impl FromStr for NoteType {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NoteType::parse(s).map(NoteType::into_owned)
+    }
+}
+
+impl<'a> NoteType<&'a str> {
+    /// Parses a note line like [`NoteType::from_str`], borrowing the
+    /// animations, colors and target notes from `s` instead of copying them.
+    pub fn parse(s: &'a str) -> Result<NoteType<&'a str>, ParseError> {
+        NoteType::parse_versioned(s, ChartVersion::default())
+    }
+
+    /// Parses a note line from a chart of the given `version`.
+    ///
+    /// Charts older than LUMINOUS have no trailing animation on holds and
+    /// slides, and no color on air notes, air traces and air slides, so
+    /// anything after the last field of those versions is ignored, and colors
+    /// are `DEF`.
+    pub fn parse_versioned(
+        s: &'a str,
+        version: ChartVersion,
+    ) -> Result<NoteType<&'a str>, ParseError> {
+        let trailing = version.has_trailing_fields();
         let (note_type, data) = s.trim().split_once('\t').unwrap();
 
         let mut data = data.trim().split('\t');
@@ -298,7 +394,7 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                animation: data.next().unwrap().to_owned(),
+                animation: data.next().unwrap(),
             }),
 
             "HLD" => NoteType::Hold(Hold {
@@ -307,7 +403,7 @@ impl FromStr for NoteType {
                 cell,
                 width,
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
-                animation: data.next().map(|x| x.to_string()),
+                animation: data.next().filter(|_| trailing),
             }),
 
             "HXD" => NoteType::HoldWithExTapHead(HoldWithExTapHead {
@@ -316,7 +412,7 @@ impl FromStr for NoteType {
                 cell,
                 width,
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
-                animation: data.next().map(|x| x.to_string()),
+                animation: data.next().filter(|_| trailing),
             }),
 
             "SLD" => NoteType::Slide(Slide {
@@ -327,7 +423,8 @@ impl FromStr for NoteType {
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
                 end_cell: data.next().unwrap().parse::<usize>().unwrap(),
                 end_width: data.next().unwrap().parse::<usize>().unwrap(),
-                animation: data.nth(1).map(|x| x.to_string()),
+                unknown: data.next().unwrap_or("SLD"),
+                animation: data.next().filter(|_| trailing),
             }),
 
             "SLC" => NoteType::SlideControlPoint(SlideControlPoint {
@@ -338,7 +435,8 @@ impl FromStr for NoteType {
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
                 end_cell: data.next().unwrap().parse::<usize>().unwrap(),
                 end_width: data.next().unwrap().parse::<usize>().unwrap(),
-                animation: data.nth(1).map(|x| x.to_string()),
+                unknown: data.next().unwrap_or("SLD"),
+                animation: data.next().filter(|_| trailing),
             }),
 
             "SXD" => NoteType::SlideWithExTapHead(SlideWithExTapHead {
@@ -349,7 +447,8 @@ impl FromStr for NoteType {
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
                 end_cell: data.next().unwrap().parse::<usize>().unwrap(),
                 end_width: data.next().unwrap().parse::<usize>().unwrap(),
-                animation: data.nth(1).map(|x| x.to_string()),
+                unknown: data.next().unwrap_or("SLD"),
+                animation: data.next().filter(|_| trailing),
             }),
 
             "SXC" => NoteType::SlideControlPointWithExTapHead(SlideControlPointWithExTapHead {
@@ -360,7 +459,8 @@ impl FromStr for NoteType {
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
                 end_cell: data.next().unwrap().parse::<usize>().unwrap(),
                 end_width: data.next().unwrap().parse::<usize>().unwrap(),
-                animation: data.nth(1).map(|x| x.to_string()),
+                unknown: data.next().unwrap_or("SLD"),
+                animation: data.next().filter(|_| trailing),
             }),
 
             "FLK" => NoteType::Flick(Flick {
@@ -368,7 +468,7 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                unknown: data.next().unwrap().to_string(),
+                unknown: data.next().unwrap(),
             }),
 
             "AIR" => NoteType::Air(Air {
@@ -376,7 +476,8 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "AUL" => NoteType::AirUpLeft(AirUpLeft {
@@ -384,7 +485,8 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "AUR" => NoteType::AirUpRight(AirUpRight {
@@ -392,7 +494,8 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "AHD" => NoteType::AirHold(AirHold {
@@ -400,8 +503,9 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "ADW" => NoteType::AirDown(AirDown {
@@ -409,7 +513,8 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "ADL" => NoteType::AirDownLeft(AirDownLeft {
@@ -417,7 +522,8 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "ADR" => NoteType::AirDownRight(AirDownRight {
@@ -425,7 +531,8 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
+                color: data.next().filter(|_| trailing).unwrap_or("DEF"),
             }),
 
             "ALD" => NoteType::AirCrush(AirCrush {
@@ -439,16 +546,16 @@ impl FromStr for NoteType {
                 end_cell: data.next().unwrap().parse::<usize>().unwrap(),
                 end_width: data.next().unwrap().parse::<usize>().unwrap(),
                 target_height: data.next().unwrap().parse::<f64>().unwrap(),
-                color: if let Some(t) = data.next() {
-                    match t {
-                        "GRY" | "RED" | "ORN" | "YEL" | "AQA" | "PPL" | "PNK" | "CYN" | "BLK"
-                        | "VLT" | "LIM" | "BLU" | "NON" | "DEF" => t.to_string(),
-                        _ => unreachable!(
-                            "invalid color found while parsing air trace / air crush. bailing."
-                        ),
-                    }
-                } else {
-                    unreachable!("cannot parse color for air trace / air crush. bailing.")
+                color: match data.next().filter(|_| trailing) {
+                    Some(
+                        t @ ("GRY" | "RED" | "ORN" | "YEL" | "AQA" | "PPL" | "PNK" | "CYN" | "BLK"
+                        | "VLT" | "LIM" | "BLU" | "NON" | "DEF"),
+                    ) => t,
+                    Some(_) => unreachable!(
+                        "invalid color found while parsing air trace / air crush. bailing."
+                    ),
+                    None if !trailing => "DEF",
+                    None => unreachable!("cannot parse color for air trace / air crush. bailing."),
                 },
             }),
 
@@ -457,22 +564,46 @@ impl FromStr for NoteType {
                 offset,
                 cell,
                 width,
-                target_note: data.next().unwrap().to_string(),
+                target_note: data.next().unwrap(),
                 starting_height: data.next().unwrap().parse::<f64>().unwrap(),
                 duration: data.next().unwrap().parse::<usize>().unwrap(),
                 end_cell: data.next().unwrap().parse::<usize>().unwrap(),
                 end_width: data.next().unwrap().parse::<usize>().unwrap(),
                 target_height: data.next().unwrap().parse::<f64>().unwrap(),
-                color: if let Some(t) = data.next() {
-                    match t {
-                        "GRY" | "RED" | "ORN" | "YEL" | "AQA" | "PPL" | "PNK" | "CYN" | "BLK"
-                        | "VLT" | "LIM" | "BLU" | "NON" | "DEF" => t.to_string(),
-                        _ => unreachable!(
-                            "invalid color found while parsing air trace / air crush. bailing."
-                        ),
-                    }
-                } else {
-                    unreachable!("cannot parse color for air trace / air crush. bailing.")
+                color: match data.next().filter(|_| trailing) {
+                    Some(
+                        t @ ("GRY" | "RED" | "ORN" | "YEL" | "AQA" | "PPL" | "PNK" | "CYN" | "BLK"
+                        | "VLT" | "LIM" | "BLU" | "NON" | "DEF"),
+                    ) => t,
+                    Some(_) => unreachable!(
+                        "invalid color found while parsing air trace / air crush. bailing."
+                    ),
+                    None if !trailing => "DEF",
+                    None => unreachable!("cannot parse color for air trace / air crush. bailing."),
+                },
+            }),
+
+            "ASC" => NoteType::AirSlideControlPoint(AirSlideControlPoint {
+                measure,
+                offset,
+                cell,
+                width,
+                target_note: data.next().unwrap(),
+                starting_height: data.next().unwrap().parse::<f64>().unwrap(),
+                duration: data.next().unwrap().parse::<usize>().unwrap(),
+                end_cell: data.next().unwrap().parse::<usize>().unwrap(),
+                end_width: data.next().unwrap().parse::<usize>().unwrap(),
+                target_height: data.next().unwrap().parse::<f64>().unwrap(),
+                color: match data.next().filter(|_| trailing) {
+                    Some(
+                        t @ ("GRY" | "RED" | "ORN" | "YEL" | "AQA" | "PPL" | "PNK" | "CYN" | "BLK"
+                        | "VLT" | "LIM" | "BLU" | "NON" | "DEF"),
+                    ) => t,
+                    Some(_) => unreachable!(
+                        "invalid color found while parsing air trace / air crush. bailing."
+                    ),
+                    None if !trailing => "DEF",
+                    None => unreachable!("cannot parse color for air trace / air crush. bailing."),
                 },
             }),
 
@@ -489,3 +620,390 @@ impl FromStr for NoteType {
         Ok(note)
     }
 }
+
+/// Expands `$body` once for every variant of [`NoteType`], binding the inner
+/// note to `$note`. Every note struct shares the universal note schema, so
+/// this lets us access those fields without matching on each variant.
+macro_rules! with_note {
+    ($value:expr, $note:ident => $body:expr) => {
+        match $value {
+            NoteType::Tap($note) => $body,
+            NoteType::ExTap($note) => $body,
+            NoteType::Hold($note) => $body,
+            NoteType::HoldWithExTapHead($note) => $body,
+            NoteType::Slide($note) => $body,
+            NoteType::SlideControlPoint($note) => $body,
+            NoteType::SlideWithExTapHead($note) => $body,
+            NoteType::SlideControlPointWithExTapHead($note) => $body,
+            NoteType::Flick($note) => $body,
+            NoteType::Air($note) => $body,
+            NoteType::AirUpRight($note) => $body,
+            NoteType::AirUpLeft($note) => $body,
+            NoteType::AirHold($note) => $body,
+            NoteType::AirDown($note) => $body,
+            NoteType::AirDownRight($note) => $body,
+            NoteType::AirDownLeft($note) => $body,
+            NoteType::AirCrush($note) => $body,
+            NoteType::AirSlide($note) => $body,
+            NoteType::AirSlideControlPoint($note) => $body,
+            NoteType::Mine($note) => $body,
+        }
+    };
+}
+
+pub(crate) use with_note;
+
+impl<S> NoteType<S> {
+    /// The tag used for the note in `.c2s` files.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            NoteType::Tap(_) => "TAP",
+            NoteType::ExTap(_) => "CHR",
+            NoteType::Hold(_) => "HLD",
+            NoteType::HoldWithExTapHead(_) => "HXD",
+            NoteType::Slide(_) => "SLD",
+            NoteType::SlideControlPoint(_) => "SLC",
+            NoteType::SlideWithExTapHead(_) => "SXD",
+            NoteType::SlideControlPointWithExTapHead(_) => "SXC",
+            NoteType::Flick(_) => "FLK",
+            NoteType::Air(_) => "AIR",
+            NoteType::AirUpRight(_) => "AUR",
+            NoteType::AirUpLeft(_) => "AUL",
+            NoteType::AirHold(_) => "AHD",
+            NoteType::AirDown(_) => "ADW",
+            NoteType::AirDownRight(_) => "ADR",
+            NoteType::AirDownLeft(_) => "ADL",
+            NoteType::AirCrush(_) => "ALD",
+            NoteType::AirSlide(_) => "ASD",
+            NoteType::AirSlideControlPoint(_) => "ASC",
+            NoteType::Mine(_) => "MNE",
+        }
+    }
+
+    /// The measure the note is placed in.
+    pub fn measure(&self) -> usize {
+        with_note!(self, note => note.measure)
+    }
+
+    /// The offset of the note from the start of its measure.
+    pub fn offset(&self) -> usize {
+        with_note!(self, note => note.offset)
+    }
+
+    /// The leftmost column the note occupies.
+    pub fn cell(&self) -> usize {
+        with_note!(self, note => note.cell)
+    }
+
+    /// The amount of columns the note occupies.
+    pub fn width(&self) -> usize {
+        with_note!(self, note => note.width)
+    }
+
+    /// The position of the note from the start of the chart, calculated with
+    /// the method described in [`ChuniChart`][crate::ChuniChart]'s
+    /// `resolution` field.
+    pub fn tick(&self, resolution: usize) -> usize {
+        self.measure() * resolution + self.offset()
+    }
+
+    /// The length of the note, for notes that have one.
+    pub fn duration(&self) -> Option<usize> {
+        match self {
+            NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => Some(note.duration),
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => Some(note.duration),
+            NoteType::AirHold(note) => Some(note.duration),
+            NoteType::AirCrush(note) => Some(note.duration),
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => Some(note.duration),
+            _ => None,
+        }
+    }
+
+    /// The cell and width the note ends at, for notes that can move across
+    /// the playfield.
+    pub fn end(&self) -> Option<(usize, usize)> {
+        match self {
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => {
+                Some((note.end_cell, note.end_width))
+            }
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => {
+                Some((note.end_cell, note.end_width))
+            }
+            NoteType::AirCrush(note) => Some((note.end_cell, note.end_width)),
+            _ => None,
+        }
+    }
+
+    /// Whether or not the note is played on the slider, rather than with the
+    /// air sensors.
+    pub fn is_ground(&self) -> bool {
+        !self.is_air()
+    }
+
+    /// Whether or not the note is played with the air sensors.
+    pub fn is_air(&self) -> bool {
+        matches!(
+            self,
+            NoteType::Air(_)
+                | NoteType::AirUpRight(_)
+                | NoteType::AirUpLeft(_)
+                | NoteType::AirHold(_)
+                | NoteType::AirDown(_)
+                | NoteType::AirDownRight(_)
+                | NoteType::AirDownLeft(_)
+                | NoteType::AirCrush(_)
+                | NoteType::AirSlide(_)
+                | NoteType::AirSlideControlPoint(_)
+        )
+    }
+}
+
+impl<S> NoteType<S> {
+    /// Converts the animation, color and target note of the note with `f`,
+    /// keeping every other field as is.
+    pub fn map<T>(self, f: impl Fn(S) -> T) -> NoteType<T> {
+        let hold = |note: Hold<S>| Hold {
+            measure: note.measure,
+            offset: note.offset,
+            cell: note.cell,
+            width: note.width,
+            duration: note.duration,
+            animation: note.animation.map(&f),
+        };
+        let slide = |note: Slide<S>| Slide {
+            measure: note.measure,
+            offset: note.offset,
+            cell: note.cell,
+            width: note.width,
+            duration: note.duration,
+            end_cell: note.end_cell,
+            end_width: note.end_width,
+            animation: note.animation.map(&f),
+            unknown: f(note.unknown),
+        };
+        let air = |note: Air<S>| Air {
+            measure: note.measure,
+            offset: note.offset,
+            cell: note.cell,
+            width: note.width,
+            target_note: f(note.target_note),
+            color: f(note.color),
+        };
+        let air_slide = |note: AirSlide<S>| AirSlide {
+            measure: note.measure,
+            offset: note.offset,
+            cell: note.cell,
+            width: note.width,
+            target_note: f(note.target_note),
+            starting_height: note.starting_height,
+            duration: note.duration,
+            end_cell: note.end_cell,
+            end_width: note.end_width,
+            target_height: note.target_height,
+            color: f(note.color),
+        };
+
+        match self {
+            NoteType::Tap(note) => NoteType::Tap(note),
+            NoteType::ExTap(note) => NoteType::ExTap(ExTap {
+                measure: note.measure,
+                offset: note.offset,
+                cell: note.cell,
+                width: note.width,
+                animation: f(note.animation),
+            }),
+            NoteType::Hold(note) => NoteType::Hold(hold(note)),
+            NoteType::HoldWithExTapHead(note) => NoteType::HoldWithExTapHead(hold(note)),
+            NoteType::Slide(note) => NoteType::Slide(slide(note)),
+            NoteType::SlideControlPoint(note) => NoteType::SlideControlPoint(slide(note)),
+            NoteType::SlideWithExTapHead(note) => NoteType::SlideWithExTapHead(slide(note)),
+            NoteType::SlideControlPointWithExTapHead(note) => {
+                NoteType::SlideControlPointWithExTapHead(slide(note))
+            }
+            NoteType::Flick(note) => NoteType::Flick(Flick {
+                measure: note.measure,
+                offset: note.offset,
+                cell: note.cell,
+                width: note.width,
+                unknown: f(note.unknown),
+            }),
+            NoteType::Air(note) => NoteType::Air(air(note)),
+            NoteType::AirUpRight(note) => NoteType::AirUpRight(air(note)),
+            NoteType::AirUpLeft(note) => NoteType::AirUpLeft(air(note)),
+            NoteType::AirHold(note) => NoteType::AirHold(AirHold {
+                measure: note.measure,
+                offset: note.offset,
+                cell: note.cell,
+                width: note.width,
+                target_note: f(note.target_note),
+                duration: note.duration,
+                color: f(note.color),
+            }),
+            NoteType::AirDown(note) => NoteType::AirDown(air(note)),
+            NoteType::AirDownRight(note) => NoteType::AirDownRight(air(note)),
+            NoteType::AirDownLeft(note) => NoteType::AirDownLeft(air(note)),
+            NoteType::AirCrush(note) => NoteType::AirCrush(AirTrace {
+                measure: note.measure,
+                offset: note.offset,
+                cell: note.cell,
+                width: note.width,
+                unknown: note.unknown,
+                starting_height: note.starting_height,
+                duration: note.duration,
+                end_cell: note.end_cell,
+                end_width: note.end_width,
+                target_height: note.target_height,
+                color: f(note.color),
+            }),
+            NoteType::AirSlide(note) => NoteType::AirSlide(air_slide(note)),
+            NoteType::AirSlideControlPoint(note) => NoteType::AirSlideControlPoint(air_slide(note)),
+            NoteType::Mine(note) => NoteType::Mine(note),
+        }
+    }
+}
+
+impl NoteType<&str> {
+    /// Copies the borrowed fields of the note into an owned [`NoteType`].
+    pub fn into_owned(self) -> NoteType {
+        self.map(String::from)
+    }
+}
+
+impl<S> NoteType<S> {
+    /// Writes the note as a `.c2s` line for a chart of the given `version`,
+    /// leaving out the fields that version does not have.
+    pub fn display(&self, version: ChartVersion) -> impl fmt::Display + '_
+    where
+        S: fmt::Display,
+    {
+        VersionedNote {
+            note: self,
+            version,
+        }
+    }
+}
+
+impl<S: fmt::Display> fmt::Display for NoteType<S> {
+    /// Writes the note as a `.c2s` line, without the trailing newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(ChartVersion::default()).fmt(f)
+    }
+}
+
+/// Writes an air height with one decimal, like the game does, or with as
+/// many as it needs to be read back as the same value.
+struct Height(f64);
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if format!("{:.1}", self.0).parse::<f64>() == Ok(self.0) {
+            write!(f, "{:.1}", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+struct VersionedNote<'a, S> {
+    note: &'a NoteType<S>,
+    version: ChartVersion,
+}
+
+impl<S: fmt::Display> fmt::Display for VersionedNote<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let trailing = self.version.has_trailing_fields();
+        let tag = self.note.tag();
+
+        with_note!(self.note, note => write!(f, "{}\t{}\t{}\t{}\t{}", tag, note.measure, note.offset, note.cell, note.width))?;
+
+        match self.note {
+            NoteType::Tap(_) | NoteType::Mine(_) => Ok(()),
+            NoteType::ExTap(note) => write!(f, "\t{}", note.animation),
+            NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => {
+                write!(f, "\t{}", note.duration)?;
+                if let Some(animation) = note.animation.as_ref().filter(|_| trailing) {
+                    write!(f, "\t{}", animation)?;
+                }
+
+                Ok(())
+            }
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => {
+                write!(
+                    f,
+                    "\t{}\t{}\t{}\t{}",
+                    note.duration, note.end_cell, note.end_width, note.unknown
+                )?;
+                if let Some(animation) = note.animation.as_ref().filter(|_| trailing) {
+                    write!(f, "\t{}", animation)?;
+                }
+
+                Ok(())
+            }
+            NoteType::Flick(note) => write!(f, "\t{}", note.unknown),
+            NoteType::Air(note)
+            | NoteType::AirUpRight(note)
+            | NoteType::AirUpLeft(note)
+            | NoteType::AirDown(note)
+            | NoteType::AirDownRight(note)
+            | NoteType::AirDownLeft(note) => {
+                write!(f, "\t{}", note.target_note)?;
+                if trailing {
+                    write!(f, "\t{}", note.color)?;
+                }
+
+                Ok(())
+            }
+            NoteType::AirHold(note) => {
+                write!(f, "\t{}\t{}", note.target_note, note.duration)?;
+                if trailing {
+                    write!(f, "\t{}", note.color)?;
+                }
+
+                Ok(())
+            }
+            NoteType::AirCrush(note) => {
+                write!(
+                    f,
+                    "\t{}\t{}\t{}\t{}\t{}\t{}",
+                    note.unknown,
+                    Height(note.starting_height),
+                    note.duration,
+                    note.end_cell,
+                    note.end_width,
+                    Height(note.target_height),
+                )?;
+                if trailing {
+                    write!(f, "\t{}", note.color)?;
+                }
+
+                Ok(())
+            }
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => {
+                write!(
+                    f,
+                    "\t{}\t{}\t{}\t{}\t{}\t{}",
+                    note.target_note,
+                    Height(note.starting_height),
+                    note.duration,
+                    note.end_cell,
+                    note.end_width,
+                    Height(note.target_height),
+                )?;
+                if trailing {
+                    write!(f, "\t{}", note.color)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
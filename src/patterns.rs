@@ -0,0 +1,241 @@
+use std::fmt;
+
+use crate::{
+    json::{Json, ToJson},
+    note::NoteType,
+    ChuniChart,
+};
+
+/// The least amount of consecutive steps that make up a pattern.
+const MIN_STEPS: usize = 4;
+
+/// A recurring construct in a chart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PatternKind {
+    /// The same cells hit over and over.
+    Jack,
+    /// Two positions hit one after the other.
+    Trill,
+    /// Cells hit further and further to one side.
+    Stairs,
+    /// Several notes hit at the same time, over and over.
+    Chord,
+    /// Taps, ExTaps or Flicks hit while a Slide is held.
+    TapSlide,
+    /// ExTaps hit one after the other.
+    ExTapRain,
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternKind::Jack => f.pad("jack"),
+            PatternKind::Trill => f.pad("trill"),
+            PatternKind::Stairs => f.pad("stairs"),
+            PatternKind::Chord => f.pad("chord"),
+            PatternKind::TapSlide => f.pad("tap-slide"),
+            PatternKind::ExTapRain => f.pad("extap rain"),
+        }
+    }
+}
+
+/// A pattern found in a chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    /// The measure the first note of the pattern is in.
+    pub start_measure: usize,
+    /// The measure the last note of the pattern is in.
+    pub end_measure: usize,
+    /// The position of the first note of the pattern.
+    pub start_tick: usize,
+    /// The position of the last note of the pattern.
+    pub end_tick: usize,
+    /// Amount of notes in the pattern.
+    pub notes: usize,
+}
+
+/// The ground notes hit at a single position.
+struct Step<'a> {
+    tick: usize,
+    notes: Vec<&'a NoteType>,
+    /// Whether or not a Slide is held at this position, not counting the
+    /// ones starting here.
+    sliding: bool,
+}
+
+impl Step<'_> {
+    /// The cell and width of the step, if it is a single note.
+    fn single(&self) -> Option<(usize, usize)> {
+        match self.notes[..] {
+            [note] => Some((note.cell(), note.width())),
+            _ => None,
+        }
+    }
+
+    fn has_tap(&self) -> bool {
+        self.notes.iter().any(|note| {
+            matches!(
+                note,
+                NoteType::Tap(_) | NoteType::ExTap(_) | NoteType::Flick(_)
+            )
+        })
+    }
+
+    fn has_ex_tap(&self) -> bool {
+        self.notes.iter().any(|note| {
+            matches!(
+                note,
+                NoteType::ExTap(_)
+                    | NoteType::HoldWithExTapHead(_)
+                    | NoteType::SlideWithExTapHead(_)
+                    | NoteType::SlideControlPointWithExTapHead(_)
+            )
+        })
+    }
+}
+
+/// Whether or not step `i` continues the pattern of the given kind that
+/// starts at step `start`.
+fn continues(kind: PatternKind, steps: &[Step], start: usize, i: usize) -> bool {
+    let (previous, step) = (&steps[i - 1], &steps[i]);
+
+    match kind {
+        PatternKind::Jack => step.single().is_some() && step.single() == previous.single(),
+        PatternKind::Trill => match (previous.single(), step.single()) {
+            (Some(a), Some(b)) if a != b => i - start < 2 || steps[i - 2].single() == Some(b),
+            _ => false,
+        },
+        PatternKind::Stairs => match (previous.single(), step.single()) {
+            (Some((a, _)), Some((b, _))) if a != b => {
+                let (first, second) = (steps[start].single(), steps[start + 1].single());
+                match (first, second) {
+                    (Some((first, _)), Some((second, _))) if i > start + 1 => {
+                        (second > first) == (b > a)
+                    }
+                    _ => true,
+                }
+            }
+            _ => false,
+        },
+        PatternKind::Chord => previous.notes.len() > 1 && step.notes.len() > 1,
+        PatternKind::TapSlide => {
+            previous.sliding && previous.has_tap() && step.sliding && step.has_tap()
+        }
+        PatternKind::ExTapRain => previous.has_ex_tap() && step.has_ex_tap(),
+    }
+}
+
+impl ChuniChart {
+    /// Groups the ground notes that are hit on their own by the position
+    /// they are hit at. Slide segments that continue a chain and Mines are
+    /// left out, while `SLC` and `SXC` segments starting one are kept.
+    fn steps(&self) -> Vec<Step<'_>> {
+        let slide_ends = self.slide_ends();
+        let slides = self
+            .notes
+            .iter()
+            .filter_map(|note| Some((note.tick(self.resolution), note.slide()?.duration)))
+            .map(|(tick, duration)| (tick, tick + duration))
+            .collect::<Vec<_>>();
+
+        let mut notes = self
+            .notes
+            .iter()
+            .filter(|note| note.is_ground() && !matches!(note, NoteType::Mine(_)))
+            .filter(|note| {
+                note.slide().is_none()
+                    || !slide_ends.contains(&(
+                        note.tick(self.resolution),
+                        note.cell(),
+                        note.width(),
+                    ))
+            })
+            .collect::<Vec<_>>();
+        notes.sort_by_key(|note| note.tick(self.resolution));
+
+        let mut steps: Vec<Step> = vec![];
+        for note in notes {
+            let tick = note.tick(self.resolution);
+            match steps.last_mut() {
+                Some(step) if step.tick == tick => step.notes.push(note),
+                _ => steps.push(Step {
+                    tick,
+                    notes: vec![note],
+                    sliding: slides
+                        .iter()
+                        .any(|(start, end)| *start < tick && tick < *end),
+                }),
+            }
+        }
+
+        steps
+    }
+
+    /// Finds jacks, trills, stairs, chords, taps over Slides and ExTap rains
+    /// in the ground notes of the chart.
+    ///
+    /// A pattern is at least 4 consecutive steps long, where a step is
+    /// every note hit at the same position. Steps more than a quarter of a
+    /// measure apart break a pattern. Patterns of different kinds may
+    /// overlap. The result is sorted by position.
+    pub fn patterns(&self) -> Vec<Pattern> {
+        let steps = self.steps();
+        let max_gap = self.resolution / 4;
+        let mut patterns = vec![];
+
+        for kind in [
+            PatternKind::Jack,
+            PatternKind::Trill,
+            PatternKind::Stairs,
+            PatternKind::Chord,
+            PatternKind::TapSlide,
+            PatternKind::ExTapRain,
+        ] {
+            let links = |start: usize, i: usize| {
+                steps[i].tick - steps[i - 1].tick <= max_gap && continues(kind, &steps, start, i)
+            };
+
+            let mut start = 0;
+            for i in 1..=steps.len() {
+                if i < steps.len() && links(start, i) {
+                    continue;
+                }
+
+                if i - start >= MIN_STEPS {
+                    let (first, last) = (&steps[start], &steps[i - 1]);
+                    patterns.push(Pattern {
+                        kind,
+                        start_measure: first.tick / self.resolution,
+                        end_measure: last.tick / self.resolution,
+                        start_tick: first.tick,
+                        end_tick: last.tick,
+                        notes: steps[start..i].iter().map(|x| x.notes.len()).sum(),
+                    });
+                }
+
+                // the last step may start the next pattern.
+                start = match i < steps.len() && links(i - 1, i) {
+                    true => i - 1,
+                    false => i,
+                };
+            }
+        }
+
+        patterns.sort_by_key(|x| (x.start_tick, x.kind));
+        patterns
+    }
+}
+
+impl ToJson for Pattern {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("kind", self.kind.to_string().into()),
+            ("start_measure", self.start_measure.into()),
+            ("end_measure", self.end_measure.into()),
+            ("start_tick", self.start_tick.into()),
+            ("end_tick", self.end_tick.into()),
+            ("notes", self.notes.into()),
+        ])
+    }
+}
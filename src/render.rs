@@ -0,0 +1,344 @@
+use std::fmt::Write;
+
+use crate::{
+    note::{AirColor, NoteType},
+    ChuniChart,
+};
+
+/// Sizes used when rendering a chart to SVG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// The width of a single column of the playfield, in pixels.
+    pub cell_width: f64,
+    /// The height of a single measure, in pixels.
+    pub measure_height: f64,
+    /// The space left free on both sides of the playfield for the BPM and
+    /// SFL labels, in pixels.
+    pub margin: f64,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            cell_width: 16.0,
+            measure_height: 192.0,
+            margin: 72.0,
+        }
+    }
+}
+
+const BACKGROUND: &str = "#101018";
+const LANE: &str = "#2a2a3a";
+const MEASURE_LINE: &str = "#c0c0d0";
+const BPM: &str = "#ff5050";
+const SFL: &str = "#50c0ff";
+const TAP: &str = "#ff4060";
+const EX_TAP: &str = "#ffd000";
+const FLICK: &str = "#40c8ff";
+const MINE: &str = "#606060";
+const HOLD: &str = "#ff9c20";
+const SLIDE: &str = "#2080ff";
+const AIR_UP: &str = "#30e060";
+const AIR_DOWN: &str = "#f040c0";
+
+impl AirColor {
+    /// The color used for notes of this [`AirColor`], or `None` if they are
+    /// not drawn. [`AirColor::Default`] resolves to `default`.
+    fn rgb<'a>(&self, default: &'a str) -> Option<&'a str> {
+        let rgb = match self {
+            AirColor::Default => default,
+            AirColor::None => return None,
+            AirColor::Gray => "#a0a0a0",
+            AirColor::Red => "#ff3030",
+            AirColor::Orange => "#ff9020",
+            AirColor::Yellow => "#ffe020",
+            AirColor::Aqua => "#30ffd0",
+            AirColor::Purple => "#a040ff",
+            AirColor::Pink => "#ff70c0",
+            AirColor::Cyan => "#20e0ff",
+            AirColor::Black => "#303030",
+            AirColor::Violet => "#8050ff",
+            AirColor::Lime => "#a0ff30",
+            AirColor::Blue => "#3060ff",
+        };
+
+        Some(rgb)
+    }
+}
+
+struct Canvas<'a> {
+    chart: &'a ChuniChart,
+    options: &'a RenderOptions,
+    height: f64,
+    out: String,
+}
+
+impl Canvas<'_> {
+    fn x(&self, cell: usize) -> f64 {
+        self.options.margin + cell as f64 * self.options.cell_width
+    }
+
+    fn y(&self, tick: usize) -> f64 {
+        self.height - tick as f64 / self.chart.resolution as f64 * self.options.measure_height
+    }
+
+    fn rect(&mut self, cell: usize, width: usize, tick: usize, height: f64, fill: &str) {
+        writeln!(
+            self.out,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+            self.x(cell) + 1.0,
+            self.y(tick) - height / 2.0,
+            width as f64 * self.options.cell_width - 2.0,
+            height,
+            fill
+        )
+        .unwrap();
+    }
+
+    /// Draws the body of a note moving from one position to another.
+    fn body(
+        &mut self,
+        (cell, width): (usize, usize),
+        (end_cell, end_width): (usize, usize),
+        tick: usize,
+        duration: usize,
+        fill: &str,
+        opacity: f64,
+    ) {
+        writeln!(
+            self.out,
+            r#"<polygon points="{:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1}" fill="{}" fill-opacity="{}"/>"#,
+            self.x(cell),
+            self.y(tick),
+            self.x(cell + width),
+            self.y(tick),
+            self.x(end_cell + end_width),
+            self.y(tick + duration),
+            self.x(end_cell),
+            self.y(tick + duration),
+            fill,
+            opacity
+        )
+        .unwrap();
+    }
+
+    /// Draws an arrow above a note. `direction` is -1 for left, 0 for
+    /// straight and 1 for right.
+    fn arrow(&mut self, cell: usize, width: usize, tick: usize, up: bool, direction: f64) {
+        let (left, right) = (self.x(cell) + 2.0, self.x(cell + width) - 2.0);
+        let y = self.y(tick) - 6.0;
+        let tip = if up { y - 14.0 } else { y + 6.0 };
+        let base = if up { y } else { y - 14.0 };
+        let shift = direction * (right - left) / 2.0;
+
+        writeln!(
+            self.out,
+            r#"<polygon points="{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}" fill="{}"/>"#,
+            left,
+            base,
+            right,
+            base,
+            (left + right) / 2.0 + shift,
+            tip,
+            if up { AIR_UP } else { AIR_DOWN }
+        )
+        .unwrap();
+    }
+
+    fn label(&mut self, x: f64, tick: usize, anchor: &str, fill: &str, text: &str) {
+        writeln!(
+            self.out,
+            r#"<text x="{:.1}" y="{:.1}" fill="{}" font-size="11" font-family="monospace" text-anchor="{}">{}</text>"#,
+            x,
+            self.y(tick) - 2.0,
+            fill,
+            anchor,
+            text
+        )
+        .unwrap();
+    }
+
+    fn line(&mut self, tick: usize, stroke: &str, width: f64) {
+        writeln!(
+            self.out,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{}"/>"#,
+            self.x(0),
+            self.y(tick),
+            self.x(16),
+            self.y(tick),
+            stroke,
+            width
+        )
+        .unwrap();
+    }
+}
+
+/// Draws a chart as a vertical SVG strip, with the start of the chart at the
+/// bottom, in the style of community chart viewers.
+///
+/// Bar lines follow the time signature designations, and BPM and SFL changes
+/// are labelled on the left and right of the playfield.
+pub fn render_svg(chart: &ChuniChart, options: &RenderOptions) -> String {
+    let resolution = chart.resolution.max(1);
+    let last_tick = chart.statistics().last_tick;
+    let measures = last_tick / resolution + 1;
+    let height = measures as f64 * options.measure_height;
+    let width = options.cell_width * 16.0 + options.margin * 2.0;
+
+    let mut canvas = Canvas {
+        chart,
+        options,
+        height,
+        out: String::new(),
+    };
+
+    writeln!(
+        canvas.out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">"#,
+        width, height, width, height
+    )
+    .unwrap();
+    writeln!(
+        canvas.out,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        BACKGROUND
+    )
+    .unwrap();
+
+    for cell in 0..=16 {
+        let x = canvas.x(cell);
+        writeln!(
+            canvas.out,
+            r#"<line x1="{:.1}" y1="0" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="1"/>"#,
+            x, x, height, LANE
+        )
+        .unwrap();
+    }
+
+    // bar lines, following every time signature until the next one.
+    let mets = chart.time_signatures();
+    for (i, (start, signature)) in mets.iter().enumerate() {
+        let end = mets
+            .get(i + 1)
+            .map(|x| x.0)
+            .unwrap_or(measures * resolution);
+        let length = signature.bar_length(resolution).max(1);
+        for tick in (*start..end).step_by(length) {
+            canvas.line(tick, MEASURE_LINE, 1.0);
+        }
+    }
+
+    for measure in 0..measures {
+        let x = canvas.x(16) + 4.0;
+        canvas.label(
+            x,
+            measure * resolution,
+            "start",
+            MEASURE_LINE,
+            &format!("#{}", measure),
+        );
+    }
+
+    for bpm in &chart.bpm {
+        let tick = bpm.beginning_measure * resolution + bpm.offset;
+        canvas.line(tick, BPM, 2.0);
+        let x = canvas.x(0) - 4.0;
+        canvas.label(x, tick, "end", BPM, &format!("{:.3}", bpm.bpm));
+    }
+
+    for sfl in &chart.sfl {
+        let tick = sfl.beginning_measure * resolution + sfl.offset;
+        canvas.line(tick, SFL, 2.0);
+        let x = canvas.x(0) - 4.0;
+        canvas.label(
+            x,
+            tick + resolution / 16,
+            "end",
+            SFL,
+            &format!("x{:.2}", sfl.multiplier),
+        );
+    }
+
+    // bodies first, so heads and arrows are drawn on top of them.
+    for note in &chart.notes {
+        let tick = note.tick(resolution);
+        let start = (note.cell(), note.width());
+
+        match note {
+            NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => {
+                canvas.body(start, start, tick, note.duration, HOLD, 0.5)
+            }
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => canvas.body(
+                start,
+                (note.end_cell, note.end_width),
+                tick,
+                note.duration,
+                SLIDE,
+                0.5,
+            ),
+            NoteType::AirHold(note) => canvas.body(start, start, tick, note.duration, AIR_UP, 0.4),
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => {
+                if let Some(fill) = note.air_color().rgb(AIR_UP) {
+                    canvas.body(
+                        start,
+                        (note.end_cell, note.end_width),
+                        tick,
+                        note.duration,
+                        fill,
+                        0.4,
+                    );
+                }
+            }
+            NoteType::AirCrush(note) => {
+                if let Some(fill) = note.air_color().rgb(AIR_DOWN) {
+                    canvas.body(
+                        start,
+                        (note.end_cell, note.end_width),
+                        tick,
+                        note.duration,
+                        fill,
+                        0.3,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for note in &chart.notes {
+        let tick = note.tick(resolution);
+        let (cell, width) = (note.cell(), note.width());
+
+        match note {
+            NoteType::Tap(_) => canvas.rect(cell, width, tick, 6.0, TAP),
+            NoteType::ExTap(_) => canvas.rect(cell, width, tick, 6.0, EX_TAP),
+            NoteType::Flick(_) => canvas.rect(cell, width, tick, 6.0, FLICK),
+            NoteType::Mine(_) => canvas.rect(cell, width, tick, 6.0, MINE),
+            NoteType::Hold(_) => canvas.rect(cell, width, tick, 6.0, HOLD),
+            NoteType::HoldWithExTapHead(_)
+            | NoteType::SlideWithExTapHead(_)
+            | NoteType::SlideControlPointWithExTapHead(_) => {
+                canvas.rect(cell, width, tick, 6.0, EX_TAP)
+            }
+            NoteType::Slide(_) => canvas.rect(cell, width, tick, 4.0, SLIDE),
+            NoteType::Air(_) => canvas.arrow(cell, width, tick, true, 0.0),
+            NoteType::AirUpLeft(_) => canvas.arrow(cell, width, tick, true, -1.0),
+            NoteType::AirUpRight(_) => canvas.arrow(cell, width, tick, true, 1.0),
+            NoteType::AirDown(_) => canvas.arrow(cell, width, tick, false, 0.0),
+            NoteType::AirDownLeft(_) => canvas.arrow(cell, width, tick, false, -1.0),
+            NoteType::AirDownRight(_) => canvas.arrow(cell, width, tick, false, 1.0),
+            NoteType::AirHold(_) | NoteType::AirSlide(_) => {
+                canvas.rect(cell, width, tick, 4.0, AIR_UP)
+            }
+            NoteType::SlideControlPoint(_)
+            | NoteType::AirSlideControlPoint(_)
+            | NoteType::AirCrush(_) => {}
+        }
+    }
+
+    canvas.out.push_str("</svg>\n");
+    canvas.out
+}
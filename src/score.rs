@@ -0,0 +1,255 @@
+use std::fmt;
+
+use crate::{
+    json::{Json, ToJson},
+    judge::JudgeResult,
+    ChuniChart,
+};
+
+/// The amount of each judgement in a play.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JudgementCounts {
+    pub justice_critical: usize,
+    pub justice: usize,
+    pub attack: usize,
+    pub miss: usize,
+}
+
+impl JudgementCounts {
+    /// The score of the play on the 1,010,000 scale, for a chart with the
+    /// given max combo.
+    ///
+    /// Every note is worth `1,000,000 / max_combo`, with a JUSTICE CRITICAL
+    /// worth 101% of that and an ATTACK 50%. The result is rounded down.
+    pub fn score(&self, max_combo: usize) -> usize {
+        if max_combo == 0 {
+            return 0;
+        }
+
+        let points = self.justice_critical * 101 + self.justice * 100 + self.attack * 50;
+        (points * 10_000 / max_combo).min(MAX_SCORE)
+    }
+
+    /// The lamp of the play. The counts have to cover every note of the
+    /// chart.
+    pub fn lamp(&self) -> Lamp {
+        match self {
+            JudgementCounts { miss: 1.., .. } => Lamp::None,
+            JudgementCounts { attack: 1.., .. } => Lamp::FullCombo,
+            JudgementCounts { justice: 1.., .. } => Lamp::AllJustice,
+            _ => Lamp::AllJusticeCritical,
+        }
+    }
+}
+
+impl JudgeResult {
+    /// The amount of each judgement in the result.
+    pub fn counts(&self) -> JudgementCounts {
+        JudgementCounts {
+            justice_critical: self.justice_critical,
+            justice: self.justice,
+            attack: self.attack,
+            miss: self.miss,
+        }
+    }
+}
+
+/// The highest possible score, with every note judged JUSTICE CRITICAL.
+pub const MAX_SCORE: usize = 1_010_000;
+
+/// The rank of a score, from D to SSS+.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rank {
+    D,
+    C,
+    B,
+    BB,
+    BBB,
+    A,
+    AA,
+    AAA,
+    S,
+    SPlus,
+    SS,
+    SSPlus,
+    SSS,
+    SSSPlus,
+}
+
+impl Rank {
+    /// The lowest score needed for each rank, from highest to lowest.
+    const BORDERS: [(usize, Rank); 13] = [
+        (1_009_000, Rank::SSSPlus),
+        (1_007_500, Rank::SSS),
+        (1_005_000, Rank::SSPlus),
+        (1_000_000, Rank::SS),
+        (990_000, Rank::SPlus),
+        (975_000, Rank::S),
+        (950_000, Rank::AAA),
+        (925_000, Rank::AA),
+        (900_000, Rank::A),
+        (800_000, Rank::BBB),
+        (700_000, Rank::BB),
+        (600_000, Rank::B),
+        (500_000, Rank::C),
+    ];
+
+    pub fn from_score(score: usize) -> Rank {
+        Rank::BORDERS
+            .iter()
+            .find(|(border, _)| score >= *border)
+            .map(|(_, rank)| *rank)
+            .unwrap_or(Rank::D)
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = match self {
+            Rank::D => "D",
+            Rank::C => "C",
+            Rank::B => "B",
+            Rank::BB => "BB",
+            Rank::BBB => "BBB",
+            Rank::A => "A",
+            Rank::AA => "AA",
+            Rank::AAA => "AAA",
+            Rank::S => "S",
+            Rank::SPlus => "S+",
+            Rank::SS => "SS",
+            Rank::SSPlus => "SS+",
+            Rank::SSS => "SSS",
+            Rank::SSSPlus => "SSS+",
+        };
+
+        f.write_str(rank)
+    }
+}
+
+/// The combo lamp of a play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lamp {
+    None,
+    /// No MISS.
+    FullCombo,
+    /// No ATTACK or MISS.
+    AllJustice,
+    /// Every note is a JUSTICE CRITICAL.
+    AllJusticeCritical,
+}
+
+impl Lamp {
+    /// The OVER POWER bonus of the lamp.
+    pub fn bonus(&self) -> f64 {
+        match self {
+            Lamp::None => 0.0,
+            Lamp::FullCombo => 0.5,
+            Lamp::AllJustice => 1.0,
+            Lamp::AllJusticeCritical => 1.25,
+        }
+    }
+}
+
+impl fmt::Display for Lamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lamp::None => f.write_str("NONE"),
+            Lamp::FullCombo => f.write_str("FULL COMBO"),
+            Lamp::AllJustice => f.write_str("ALL JUSTICE"),
+            Lamp::AllJusticeCritical => f.write_str("ALL JUSTICE CRITICAL"),
+        }
+    }
+}
+
+/// The play rating of a score, in hundredths, without rounding down.
+fn rating_hundredths(score: usize, constant: f64) -> f64 {
+    let s = score as f64;
+    let c = constant * 100.0;
+
+    let rating = match score {
+        1_009_000.. => c + 215.0,
+        1_007_500.. => c + 200.0 + (s - 1_007_500.0) / 100.0,
+        1_005_000.. => c + 150.0 + (s - 1_005_000.0) / 50.0,
+        1_000_000.. => c + 100.0 + (s - 1_000_000.0) / 100.0,
+        975_000.. => c + (s - 975_000.0) / 250.0,
+        925_000.. => c - 300.0 + (s - 925_000.0) * 3.0 / 500.0,
+        900_000.. => c - 500.0 + (s - 900_000.0) / 125.0,
+        800_000.. => (c - 500.0) / 2.0 + (s - 800_000.0) * ((c - 500.0) / 2.0) / 100_000.0,
+        500_000.. => (s - 500_000.0) * ((c - 500.0) / 2.0) / 300_000.0,
+        _ => 0.0,
+    };
+
+    rating.max(0.0)
+}
+
+/// The play rating of a score on a chart with the given level constant,
+/// rounded down to two decimals like in game. It does not depend on the
+/// lamp.
+pub fn rating(score: usize, constant: f64) -> f64 {
+    // the small epsilon keeps values like 14.8 * 100 from rounding down to
+    // 1479.
+    (rating_hundredths(score, constant) + 1e-6).floor() / 100.0
+}
+
+/// The OVER POWER of a score on a chart with the given level constant,
+/// including the bonus of the lamp. A chart is worth at most
+/// `(constant + 3) * 5`.
+pub fn over_power(score: usize, constant: f64, lamp: Lamp) -> f64 {
+    let base = match score {
+        1_007_500.. => (constant + 2.0) * 5.0 + (score - 1_007_500) as f64 * 0.0015,
+        _ => rating_hundredths(score, constant) / 100.0 * 5.0,
+    };
+
+    base + lamp.bonus()
+}
+
+/// Everything a play is ranked with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreCard {
+    pub score: usize,
+    pub rank: Rank,
+    pub lamp: Lamp,
+    pub rating: f64,
+    pub over_power: f64,
+}
+
+impl ScoreCard {
+    /// Computes the score card of a play on a chart with the given max combo
+    /// and level constant.
+    pub fn new(counts: &JudgementCounts, max_combo: usize, constant: f64) -> ScoreCard {
+        let score = counts.score(max_combo);
+        let lamp = counts.lamp();
+
+        ScoreCard {
+            score,
+            rank: Rank::from_score(score),
+            lamp,
+            rating: rating(score, constant),
+            over_power: over_power(score, constant, lamp),
+        }
+    }
+}
+
+impl ChuniChart {
+    /// Computes the score card of a play of the chart, using the amount of
+    /// [`ChuniChart::judgement_ticks`] as the max combo and `level` as the
+    /// level constant.
+    ///
+    /// `level` is usually 0.0 in the chart itself, so it should be set from
+    /// `Music.xml` first, with [`MusicInfo::attach`][crate::music_xml::MusicInfo::attach].
+    pub fn score_card(&self, counts: &JudgementCounts) -> ScoreCard {
+        ScoreCard::new(counts, self.judgement_ticks().len(), self.level)
+    }
+}
+
+impl ToJson for ScoreCard {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("score", self.score.into()),
+            ("rank", self.rank.to_string().into()),
+            ("lamp", self.lamp.to_string().into()),
+            ("rating", self.rating.into()),
+            ("over_power", self.over_power.into()),
+        ])
+    }
+}
@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+use crate::{timing::TempoMap, ChuniChart};
+
+/// A point where the scroll speed of the chart changes.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Segment {
+    /// The position of the change, from the start of the chart.
+    tick: usize,
+    /// How far the playfield has scrolled at the change, in ticks.
+    position: f64,
+    multiplier: f64,
+}
+
+/// Tracks how far the playfield has scrolled, following the SFL designations
+/// of a chart.
+///
+/// Notes scroll by one tick per tick at normal speed, so they speed up with
+/// the BPM, while SFL designations multiply that speed. Where two of them
+/// overlap, the one starting later takes over.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScrollMap {
+    tempo: TempoMap,
+    segments: Vec<Segment>,
+}
+
+impl ScrollMap {
+    /// Builds the scroll map of a chart. The scroll speed is normal wherever
+    /// no SFL designation is in effect.
+    pub fn new(chart: &ChuniChart) -> ScrollMap {
+        let mut sfls = chart
+            .sfl
+            .iter()
+            .map(|x| {
+                let tick = x.beginning_measure * chart.resolution + x.offset;
+                (tick, tick + x.duration, x.multiplier)
+            })
+            .collect::<Vec<_>>();
+        sfls.sort_by_key(|(tick, _, _)| *tick);
+
+        let mut changes = vec![(0, 1.0)];
+        for (i, (tick, end, multiplier)) in sfls.iter().enumerate() {
+            changes.push((*tick, *multiplier));
+            if sfls.get(i + 1).is_none_or(|(next, _, _)| next > end) {
+                changes.push((*end, 1.0));
+            }
+        }
+
+        let mut map = ScrollMap {
+            tempo: chart.tempo_map(),
+            segments: Vec::with_capacity(changes.len()),
+        };
+
+        for (tick, multiplier) in changes {
+            if map.segments.last().is_some_and(|x| x.tick == tick) {
+                map.segments.pop();
+            }
+
+            let position = map.position(tick as f64);
+            map.segments.push(Segment {
+                tick,
+                position,
+                multiplier,
+            });
+        }
+
+        map
+    }
+
+    fn segment_at(&self, tick: f64) -> Option<&Segment> {
+        self.segments.iter().rev().find(|x| x.tick as f64 <= tick)
+    }
+
+    /// The scroll speed multiplier at the given position.
+    pub fn multiplier(&self, tick: usize) -> f64 {
+        self.segment_at(tick as f64)
+            .map(|x| x.multiplier)
+            .unwrap_or(1.0)
+    }
+
+    /// How far the playfield has scrolled when reaching the given position,
+    /// in ticks at normal speed. This goes down while scrolling backwards.
+    pub fn position(&self, tick: f64) -> f64 {
+        match self.segment_at(tick) {
+            Some(segment) => segment.position + (tick - segment.tick as f64) * segment.multiplier,
+            None => tick,
+        }
+    }
+
+    /// How far a note at the given position is from the judgement line at
+    /// `ms` milliseconds from the start of the chart, in ticks at normal
+    /// speed. Notes that were already judged have a negative distance, as do
+    /// notes scrolled past the line backwards.
+    pub fn distance(&self, tick: usize, ms: f64) -> f64 {
+        self.position(tick as f64) - self.position(self.tempo.tick(ms))
+    }
+
+    /// Every part of the chart that does not scroll at normal speed, with
+    /// its multiplier, sorted by position.
+    pub fn changes(&self) -> Vec<(Range<usize>, f64)> {
+        self.segments
+            .windows(2)
+            .filter(|pair| pair[0].multiplier != 1.0)
+            .map(|pair| (pair[0].tick..pair[1].tick, pair[0].multiplier))
+            .collect()
+    }
+
+    /// How long a note at the given position is on screen before reaching
+    /// the judgement line, in milliseconds. The lane shows `lane_length`
+    /// ticks at normal speed, and notes only count from the last time they
+    /// came into view, as a stop or a reverse scroll can show them early
+    /// and hide them again.
+    ///
+    /// Notes are on screen before the chart starts, so the ones near its
+    /// start scroll in at normal speed and the starting BPM.
+    pub fn approach_time(&self, tick: usize, lane_length: f64) -> f64 {
+        let end = self.position(tick as f64);
+        let (low, high) = (end - lane_length, end);
+        let outside = |position: f64| position < low || position > high;
+
+        let mut since = low.min(0.0);
+        let mut until = tick as f64;
+        for segment in self.segments.iter().rev() {
+            let start = segment.tick as f64;
+            if start >= until {
+                continue;
+            }
+
+            if outside(segment.position) {
+                let bound = if segment.position < low { low } else { high };
+                since = start + (bound - segment.position) / segment.multiplier;
+                break;
+            }
+            until = start;
+        }
+
+        self.ms(tick as f64) - self.ms(since)
+    }
+
+    /// Converts a fractional position to time, carrying on at the starting
+    /// BPM before the start of the chart.
+    fn ms(&self, tick: f64) -> f64 {
+        let base = tick.floor().max(0.0) as usize;
+        let length = self.tempo.ms(base + 1) - self.tempo.ms(base);
+
+        self.tempo.ms(base) + (tick - base as f64) * length
+    }
+
+    /// The tempo map the times given to [`ScrollMap::distance`] are
+    /// converted with.
+    pub fn tempo_map(&self) -> &TempoMap {
+        &self.tempo
+    }
+}
+
+impl ChuniChart {
+    /// Builds the [`ScrollMap`] of the chart.
+    pub fn scroll_map(&self) -> ScrollMap {
+        ScrollMap::new(self)
+    }
+}
@@ -1,9 +1,19 @@
-use std::{str::FromStr, string::ParseError};
+use std::{fmt, str::FromStr, string::ParseError};
 
-#[derive(Debug, Default, PartialEq)]
+/// Scroll speed change for a part of the chart, written as
+/// `SFL measure offset duration multiplier`.
+///
+/// Notes scroll `multiplier` times as fast for `duration` ticks, going back
+/// to normal speed afterwards. A multiplier of 0 stops the notes, and a
+/// negative one scrolls them backwards.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Sfl {
+    /// The starting measure of the change.
     pub beginning_measure: usize,
+    /// The offset of the change. This is calculated in the same method as
+    /// described in [`ChuniChart`][crate::ChuniChart]'s `resolution` field.
     pub offset: usize,
+    /// How long the change lasts, in ticks.
     pub duration: usize,
     pub multiplier: f64,
 }
@@ -12,7 +22,8 @@ impl FromStr for Sfl {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut data = s.trim().split('\t');
+        let (_, data) = s.trim().split_once('\t').unwrap();
+        let mut data = data.trim().split('\t');
 
         let beginning_measure = data.next().unwrap().parse::<usize>().unwrap();
         let offset = data.next().unwrap().parse::<usize>().unwrap();
@@ -27,3 +38,13 @@ impl FromStr for Sfl {
         })
     }
 }
+
+impl fmt::Display for Sfl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SFL\t{}\t{}\t{}\t{:.6}",
+            self.beginning_measure, self.offset, self.duration, self.multiplier
+        )
+    }
+}
@@ -0,0 +1,98 @@
+use std::ops::Range;
+
+use crate::{
+    events::C2sEvent,
+    json::{Json, ToJson},
+    lint::Source,
+};
+
+/// Where a single line of a `.c2s` file was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// The line number, starting from 1.
+    pub line: usize,
+    /// The bytes of the line in the file, without surrounding whitespace or
+    /// the line break.
+    pub range: Range<usize>,
+}
+
+impl ToJson for Span {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("line", self.line.into()),
+            ("start", self.range.start.into()),
+            ("end", self.range.end.into()),
+        ])
+    }
+}
+
+/// The [`Span`] of every header, BPM, MET, SFL, note and extra line of a
+/// chart, made with [`ChuniChart::parse_with_spans`][crate::ChuniChart::parse_with_spans].
+///
+/// Every list is in the same order as the matching list of the chart, so
+/// `notes[i]` is where `chart.notes[i]` was read from. Headers are listed
+/// with their tag, in the order they were read. Spans are not kept up to
+/// date when the chart is changed afterwards.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SourceMap {
+    pub headers: Vec<(&'static str, Span)>,
+    pub bpm: Vec<Span>,
+    pub met: Vec<Span>,
+    pub sfl: Vec<Span>,
+    pub notes: Vec<Span>,
+    pub extra: Vec<Span>,
+}
+
+impl SourceMap {
+    /// Records `span` for `event`.
+    pub(crate) fn push<S>(&mut self, event: &C2sEvent<S>, span: Span) {
+        match event {
+            C2sEvent::Header(header) => self.headers.push((header.tag(), span)),
+            C2sEvent::Bpm(_) => self.bpm.push(span),
+            C2sEvent::Met(_) => self.met.push(span),
+            C2sEvent::Sfl(_) => self.sfl.push(span),
+            C2sEvent::Note(_) => self.notes.push(span),
+            C2sEvent::Statistic(_) | C2sEvent::Unknown(_) => self.extra.push(span),
+        }
+    }
+
+    /// The span of the line `source` points to. This is how a
+    /// [`Lint`][crate::lint::Lint] is traced back to the file.
+    ///
+    /// A header that is written more than once points to its last line, as
+    /// that is the one the chart keeps.
+    pub fn get(&self, source: Source) -> Option<&Span> {
+        match source {
+            Source::Header(tag) => self
+                .headers
+                .iter()
+                .rev()
+                .find(|(x, _)| *x == tag)
+                .map(|(_, span)| span),
+            Source::Bpm(i) => self.bpm.get(i),
+            Source::Met(i) => self.met.get(i),
+            Source::Sfl(i) => self.sfl.get(i),
+            Source::Note(i) => self.notes.get(i),
+        }
+    }
+}
+
+/// Splits `input` into its lines that are not empty, with their [`Span`].
+pub(crate) fn lines(input: &str) -> impl Iterator<Item = (Span, &str)> {
+    let mut start = 0;
+
+    input
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(i, line)| {
+            let begin = start + (line.len() - line.trim_start().len());
+            start += line.len();
+
+            let line = line.trim();
+            let span = Span {
+                line: i + 1,
+                range: begin..begin + line.len(),
+            };
+            (!line.is_empty()).then_some((span, line))
+        })
+}
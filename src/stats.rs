@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    json::{Json, ToJson},
+    note::NoteType,
+    ChuniChart,
+};
+
+/// Note counts and lengths of a chart. The counts follow the same grouping
+/// as the `T_REC_*` lines in the footer of `.c2s` files.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Statistics {
+    /// Amount of Taps.
+    pub taps: usize,
+    /// Amount of ExTaps.
+    pub ex_taps: usize,
+    /// Amount of Flicks.
+    pub flicks: usize,
+    /// Amount of Mines.
+    pub mines: usize,
+    /// Amount of Holds, including HoldWithExTapHeads.
+    pub holds: usize,
+    /// Amount of Slide segments, including control points and segments with
+    /// ExTap heads.
+    pub slides: usize,
+    /// Amount of Air notes, in all directions.
+    pub airs: usize,
+    /// Amount of AirHolds and AirSlides. AirSlide control points are not
+    /// counted.
+    pub air_holds: usize,
+    /// Amount of AirCrushes.
+    pub air_crushes: usize,
+    /// Amount of notes played with each ExTap animation, including the heads
+    /// of Holds and Slides.
+    pub animations: BTreeMap<String, usize>,
+    /// Total length of all Holds.
+    pub hold_length: usize,
+    /// Total length of all Slide segments.
+    pub slide_length: usize,
+    /// Total length of all AirHolds and AirSlide segments.
+    pub air_hold_length: usize,
+    /// The position of the first note in the chart.
+    pub first_tick: usize,
+    /// The position at which the last note in the chart ends.
+    pub last_tick: usize,
+}
+
+impl Statistics {
+    /// Total amount of notes, with the same grouping as `T_REC_ALL`.
+    pub fn total(&self) -> usize {
+        self.taps
+            + self.ex_taps
+            + self.flicks
+            + self.mines
+            + self.holds
+            + self.slides
+            + self.airs
+            + self.air_holds
+    }
+}
+
+impl ChuniChart {
+    /// Counts the notes in the chart.
+    pub fn statistics(&self) -> Statistics {
+        let mut stats = Statistics {
+            first_tick: usize::MAX,
+            ..Default::default()
+        };
+
+        for note in &self.notes {
+            match note {
+                NoteType::Tap(_) => stats.taps += 1,
+                NoteType::ExTap(note) => {
+                    stats.ex_taps += 1;
+                    *stats.animations.entry(note.animation.clone()).or_default() += 1;
+                }
+                NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => {
+                    stats.holds += 1;
+                    stats.hold_length += note.duration;
+                    if let Some(animation) = &note.animation {
+                        *stats.animations.entry(animation.clone()).or_default() += 1;
+                    }
+                }
+                NoteType::Slide(note)
+                | NoteType::SlideControlPoint(note)
+                | NoteType::SlideWithExTapHead(note)
+                | NoteType::SlideControlPointWithExTapHead(note) => {
+                    stats.slides += 1;
+                    stats.slide_length += note.duration;
+                    if let Some(animation) = &note.animation {
+                        *stats.animations.entry(animation.clone()).or_default() += 1;
+                    }
+                }
+                NoteType::Flick(_) => stats.flicks += 1,
+                NoteType::Mine(_) => stats.mines += 1,
+                NoteType::Air(_)
+                | NoteType::AirUpRight(_)
+                | NoteType::AirUpLeft(_)
+                | NoteType::AirDown(_)
+                | NoteType::AirDownRight(_)
+                | NoteType::AirDownLeft(_) => stats.airs += 1,
+                NoteType::AirHold(note) => {
+                    stats.air_holds += 1;
+                    stats.air_hold_length += note.duration;
+                }
+                NoteType::AirSlide(note) => {
+                    stats.air_holds += 1;
+                    stats.air_hold_length += note.duration;
+                }
+                NoteType::AirSlideControlPoint(note) => stats.air_hold_length += note.duration,
+                NoteType::AirCrush(_) => stats.air_crushes += 1,
+            }
+
+            let tick = note.tick(self.resolution);
+            stats.first_tick = stats.first_tick.min(tick);
+            stats.last_tick = stats
+                .last_tick
+                .max(tick + note.duration().unwrap_or_default());
+        }
+
+        if self.notes.is_empty() {
+            stats.first_tick = 0;
+        }
+
+        stats
+    }
+}
+
+impl ToJson for Statistics {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("taps", self.taps.into()),
+            ("ex_taps", self.ex_taps.into()),
+            ("flicks", self.flicks.into()),
+            ("mines", self.mines.into()),
+            ("holds", self.holds.into()),
+            ("slides", self.slides.into()),
+            ("airs", self.airs.into()),
+            ("air_holds", self.air_holds.into()),
+            ("air_crushes", self.air_crushes.into()),
+            ("total", self.total().into()),
+            (
+                "animations",
+                Json::Object(
+                    self.animations
+                        .iter()
+                        .map(|(animation, count)| (animation.clone(), (*count).into()))
+                        .collect(),
+                ),
+            ),
+            ("hold_length", self.hold_length.into()),
+            ("slide_length", self.slide_length.into()),
+            ("air_hold_length", self.air_hold_length.into()),
+            ("first_tick", self.first_tick.into()),
+            ("last_tick", self.last_tick.into()),
+        ])
+    }
+}
@@ -0,0 +1,101 @@
+use crate::ChuniChart;
+
+/// A point where the tempo of the chart changes.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Segment {
+    /// The position of the change, from the start of the chart.
+    tick: usize,
+    /// The time of the change, in milliseconds.
+    ms: f64,
+    bpm: f64,
+}
+
+/// Converts positions in a chart to time, following its BPM designations.
+///
+/// Positions are expressed in ticks from the start of the chart, calculated
+/// with the method described in [`ChuniChart`]'s `resolution` field. The BPM
+/// always counts quarter notes, so a measure is 4 beats long, no matter the
+/// time signature.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TempoMap {
+    resolution: usize,
+    segments: Vec<Segment>,
+}
+
+impl TempoMap {
+    /// Builds the tempo map of a chart. If the chart does not designate a BPM
+    /// at its start, the starting BPM from `BPM_DEF` is used until the first
+    /// designation.
+    pub fn new(chart: &ChuniChart) -> TempoMap {
+        let mut bpms = chart
+            .bpm
+            .iter()
+            .map(|x| (x.beginning_measure * chart.resolution + x.offset, x.bpm))
+            .collect::<Vec<_>>();
+        bpms.sort_by_key(|(tick, _)| *tick);
+
+        if bpms.first().is_none_or(|(tick, _)| *tick != 0) {
+            bpms.insert(0, (0, chart.bpm_def.starting_bpm));
+        }
+
+        let mut map = TempoMap {
+            resolution: chart.resolution,
+            segments: Vec::with_capacity(bpms.len()),
+        };
+
+        for (tick, bpm) in bpms {
+            let ms = map.ms(tick);
+            map.segments.push(Segment { tick, ms, bpm });
+        }
+
+        map
+    }
+
+    /// The length of a tick at the given BPM, in milliseconds.
+    fn tick_length(&self, bpm: f64) -> f64 {
+        60000.0 / bpm / (self.resolution as f64 / 4.0)
+    }
+
+    fn segment_at(&self, tick: usize) -> Option<&Segment> {
+        self.segments.iter().rev().find(|x| x.tick <= tick)
+    }
+
+    /// Converts a position to the time it is played at, in milliseconds from
+    /// the start of the chart.
+    pub fn ms(&self, tick: usize) -> f64 {
+        match self.segment_at(tick) {
+            Some(segment) => {
+                segment.ms + (tick - segment.tick) as f64 * self.tick_length(segment.bpm)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Converts a time in milliseconds back to a position. The result is
+    /// fractional, as a time usually falls between two ticks.
+    pub fn tick(&self, ms: f64) -> f64 {
+        match self.segments.iter().rev().find(|x| x.ms <= ms) {
+            Some(segment) => {
+                segment.tick as f64 + (ms - segment.ms) / self.tick_length(segment.bpm)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// The BPM at the given position.
+    pub fn bpm(&self, tick: usize) -> f64 {
+        self.segment_at(tick).map(|x| x.bpm).unwrap_or_default()
+    }
+
+    /// The resolution of the chart this map was built from.
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+}
+
+impl ChuniChart {
+    /// Builds the [`TempoMap`] of the chart.
+    pub fn tempo_map(&self) -> TempoMap {
+        TempoMap::new(self)
+    }
+}
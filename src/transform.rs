@@ -0,0 +1,329 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::{
+    note::{with_note, NoteType},
+    ChuniChart,
+};
+
+/// Flips the chart horizontally, like the MIRROR modifier in game.
+///
+/// Directional ExTap animations and diagonal air notes are flipped along
+/// with the notes.
+///
+/// Returns the index of every note sticking out of the playfield, which
+/// has no place on the other side, and is moved to the left edge instead.
+pub fn mirror(chart: &mut ChuniChart) -> Vec<usize> {
+    let mut outside = vec![];
+    for (i, note) in chart.notes.iter_mut().enumerate() {
+        let mut fits = true;
+        let mut flip = |cell: usize, width: usize| {
+            16usize.checked_sub(cell + width).unwrap_or_else(|| {
+                fits = false;
+                0
+            })
+        };
+        with_note!(&mut *note, note => note.cell = flip(note.cell, note.width));
+
+        match note {
+            NoteType::ExTap(note) => note.animation = mirror_animation(&note.animation),
+            NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => {
+                note.animation = note.animation.as_deref().map(mirror_animation);
+            }
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => {
+                note.end_cell = flip(note.end_cell, note.end_width);
+                note.animation = note.animation.as_deref().map(mirror_animation);
+            }
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => {
+                note.end_cell = flip(note.end_cell, note.end_width);
+            }
+            NoteType::AirCrush(note) => note.end_cell = flip(note.end_cell, note.end_width),
+            _ => {}
+        }
+
+        if !fits {
+            outside.push(i);
+        }
+
+        let flipped = match note {
+            NoteType::AirUpRight(note) => Some(NoteType::AirUpLeft(note.clone())),
+            NoteType::AirUpLeft(note) => Some(NoteType::AirUpRight(note.clone())),
+            NoteType::AirDownRight(note) => Some(NoteType::AirDownLeft(note.clone())),
+            NoteType::AirDownLeft(note) => Some(NoteType::AirDownRight(note.clone())),
+            _ => None,
+        };
+
+        if let Some(flipped) = flipped {
+            *note = flipped;
+        }
+    }
+
+    outside
+}
+
+fn mirror_animation(animation: &str) -> String {
+    match animation {
+        "LS" => "RS",
+        "RS" => "LS",
+        "LC" => "RC",
+        "RC" => "LC",
+        animation => animation,
+    }
+    .to_string()
+}
+
+/// A small seeded random number generator (SplitMix64), so every modifier
+/// gives the same chart for the same seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut x = self.0;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+
+    /// A random number from `low` to `high`, both included.
+    fn range(&mut self, low: isize, high: isize) -> isize {
+        low + (self.next() % (high - low + 1) as u64) as isize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range(0, i as isize) as usize);
+        }
+    }
+}
+
+/// Moves every note with `place`, which is given the index of a note, and
+/// the cell and width of either end of it, and returns the new cell.
+fn place(chart: &mut ChuniChart, place: impl Fn(usize, usize, usize) -> usize) {
+    for (i, note) in chart.notes.iter_mut().enumerate() {
+        with_note!(&mut *note, note => note.cell = place(i, note.cell, note.width));
+
+        match note {
+            NoteType::Slide(note)
+            | NoteType::SlideControlPoint(note)
+            | NoteType::SlideWithExTapHead(note)
+            | NoteType::SlideControlPointWithExTapHead(note) => {
+                note.end_cell = place(i, note.end_cell, note.end_width);
+            }
+            NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note) => {
+                note.end_cell = place(i, note.end_cell, note.end_width);
+            }
+            NoteType::AirCrush(note) => note.end_cell = place(i, note.end_cell, note.end_width),
+            _ => {}
+        }
+    }
+}
+
+/// Turns a shuffle of the 16 cells into the new start of every cell, for
+/// every width of note.
+///
+/// A note moves to the new place of its first cell when it fits on the
+/// playfield there. The notes that would stick out take the starts left
+/// over, from left to right, so notes of the same width that started at
+/// different cells still do after the shuffle.
+fn starts(cells: &[usize; 16]) -> Vec<Vec<usize>> {
+    (0..=16)
+        .map(|width: usize| {
+            let last = 16 - width.max(1);
+            let starts = (0..=last)
+                .map(|cell| Some(cells[cell]).filter(|x| *x <= last))
+                .collect::<Vec<_>>();
+
+            let mut left = (0..=last)
+                .filter(|x| !starts.contains(&Some(*x)))
+                .collect::<Vec<_>>()
+                .into_iter();
+            starts
+                .into_iter()
+                .map(|start| start.or_else(|| left.next()).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+/// Shuffles the four groups of 4 cells, like the RANDOM modifier in game.
+/// Notes keep their place inside their group.
+pub fn random(chart: &mut ChuniChart, seed: u64) {
+    let mut groups = [0, 1, 2, 3];
+    Rng(seed).shuffle(&mut groups);
+
+    let starts = starts(&std::array::from_fn(|i| groups[i / 4] * 4 + i % 4));
+    place(chart, |_, cell, width| {
+        starts[width.min(16)][cell.min(16 - width.max(1))]
+    });
+}
+
+/// Shuffles all 16 cells, keeping the width of every note.
+pub fn full_random(chart: &mut ChuniChart, seed: u64) {
+    let mut cells: [usize; 16] = std::array::from_fn(|i| i);
+    Rng(seed).shuffle(&mut cells);
+
+    let starts = starts(&cells);
+    place(chart, |_, cell, width| {
+        starts[width.min(16)][cell.min(16 - width.max(1))]
+    });
+}
+
+/// The group the `i`th note is in, for [`s_random`].
+fn root(groups: &mut [usize], i: usize) -> usize {
+    let mut i = i;
+    while groups[i] != i {
+        groups[i] = groups[groups[i]];
+        i = groups[i];
+    }
+    i
+}
+
+/// Moves every note to a random cell on its own, like the S-RANDOM modifier
+/// in game.
+///
+/// Notes that have to stay together are moved together by the same amount:
+/// the segments of a slide chain, and air notes with the ground note they
+/// sit on. Every move keeps the notes on the playfield and their width.
+pub fn s_random(chart: &mut ChuniChart, seed: u64) {
+    let resolution = chart.resolution;
+    let notes = &chart.notes;
+
+    // every note starts as its own group, and is joined with the notes it
+    // has to stay with.
+    let mut groups = (0..notes.len()).collect::<Vec<_>>();
+
+    // where ground notes start, and where any note ends, which is where
+    // slide chains continue and air notes sit.
+    let mut anchors: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, note) in notes.iter().enumerate() {
+        let tick = note.tick(resolution);
+        if note.is_ground() {
+            anchors
+                .entry((tick, note.cell(), note.width()))
+                .or_default()
+                .push(i);
+        }
+
+        let end = tick + note.duration().unwrap_or_default();
+        let (cell, width) = note.end().unwrap_or((note.cell(), note.width()));
+        anchors.entry((end, cell, width)).or_default().push(i);
+    }
+
+    for (i, note) in notes.iter().enumerate() {
+        if !note.is_air() && note.slide().is_none() {
+            continue;
+        }
+
+        let key = (note.tick(resolution), note.cell(), note.width());
+        for j in anchors.get(&key).into_iter().flatten() {
+            let (a, b) = (root(&mut groups, i), root(&mut groups, *j));
+            groups[a] = b;
+        }
+    }
+
+    // the furthest each group can move to either side.
+    let mut bounds = HashMap::new();
+    for (i, note) in notes.iter().enumerate() {
+        let (cell, width) = (note.cell(), note.width().min(16));
+        let (end_cell, end_width) = note.end().unwrap_or((cell, width));
+
+        let left = -(cell.min(end_cell) as isize);
+        let right = 16 - (cell + width).max(end_cell + end_width.min(16)) as isize;
+
+        let bound = bounds
+            .entry(root(&mut groups, i))
+            .or_insert((isize::MIN, isize::MAX));
+        *bound = (bound.0.max(left), bound.1.min(right));
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..notes.len() {
+        members.entry(root(&mut groups, i)).or_default().push(i);
+    }
+    // the widest notes are placed first, as they have the fewest places
+    // left to go.
+    let mut roots = members.keys().copied().collect::<Vec<_>>();
+    roots.sort_by_key(|root| {
+        let notes = members[root].iter().map(|i| &notes[*i]);
+        let width = notes.clone().map(|note| note.width()).max();
+        (
+            notes.map(|note| note.tick(resolution)).min(),
+            Reverse(width),
+            *root,
+        )
+    });
+
+    // groups are only moved where their notes don't land on top of notes
+    // hit at the same time, unless there is no such place.
+    let mut widths: HashMap<(usize, bool), usize> = HashMap::new();
+    for note in notes {
+        *widths
+            .entry((note.tick(resolution), note.is_air()))
+            .or_default() += note.width();
+    }
+
+    let mut rng = Rng(seed);
+    let mut taken: HashMap<(usize, bool), Vec<(isize, isize)>> = HashMap::new();
+    let mut offsets = vec![0; notes.len()];
+    for root in roots {
+        let (left, right) = bounds[&root];
+        let cells = |offset: isize| {
+            members[&root].iter().map(move |i| {
+                let note = &notes[*i];
+                let cell = note.cell() as isize + offset;
+                let key = (note.tick(resolution), note.is_air());
+                (key, (cell, cell + note.width() as isize))
+            })
+        };
+        let free = |offset: isize| {
+            cells(offset).all(|(key, (start, end))| {
+                taken
+                    .get(&key)
+                    .into_iter()
+                    .flatten()
+                    .all(|(x, y)| end <= *x || *y <= start)
+            })
+        };
+
+        // when more than half of the playfield is hit at once, notes are
+        // packed against the edges and each other, so there is room left
+        // for all of them.
+        let crowded = cells(0).any(|(key, _)| widths[&key] > 8);
+        let touching = |offset: isize| {
+            cells(offset).any(|(key, (start, end))| {
+                start == 0
+                    || end == 16
+                    || taken
+                        .get(&key)
+                        .into_iter()
+                        .flatten()
+                        .any(|(x, y)| end == *x || *y == start)
+            })
+        };
+
+        let free = (left..=right).filter(|x| free(*x)).collect::<Vec<_>>();
+        let packed = free
+            .iter()
+            .copied()
+            .filter(|x| touching(*x))
+            .collect::<Vec<_>>();
+        let offset = match (crowded && !packed.is_empty(), &free[..]) {
+            _ if left > right => 0,
+            (true, _) => packed[rng.range(0, packed.len() as isize - 1) as usize],
+            (false, []) => rng.range(left, right),
+            (false, free) => free[rng.range(0, free.len() as isize - 1) as usize],
+        };
+
+        for (key, span) in cells(offset).collect::<Vec<_>>() {
+            taken.entry(key).or_default().push(span);
+        }
+        for i in &members[&root] {
+            offsets[*i] = offset;
+        }
+    }
+
+    place(chart, |i, cell, _| (cell as isize + offsets[i]) as usize);
+}
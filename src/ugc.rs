@@ -0,0 +1,307 @@
+use std::fmt::Write;
+
+use crate::{note::NoteType, ChuniChart};
+
+/// The amount of ticks in a beat in UMIGURI charts.
+const TICKS: usize = 480;
+
+/// Converts a chart to the UMIGURI (`.ugc`) chart format.
+///
+/// Taps, ExTaps, Flicks, Mines, Holds, Slides and Air notes are converted.
+/// AirHolds, AirSlides and AirCrushes are not converted yet, and are left
+/// out of the output. Cells and widths are written as a single base 36 digit,
+/// so larger ones are clamped to `Z`; see [`clamped`].
+pub fn to_ugc(chart: &ChuniChart) -> String {
+    let layout = BarLayout::new(chart);
+    let mut out = String::new();
+
+    writeln!(out, "@VER\t6").unwrap();
+    writeln!(out, "@DESIGN\t{}", chart.creator).unwrap();
+    writeln!(out, "@DIFF\t{}", chart.difficult).unwrap();
+    writeln!(out, "@CONST\t{:.6}", chart.level).unwrap();
+    writeln!(out, "@MAINBPM\t{:.6}", chart.bpm_def.starting_bpm).unwrap();
+    writeln!(out, "@TICKS\t{}", TICKS).unwrap();
+
+    for bar in &layout.bars {
+        writeln!(
+            out,
+            "@BEAT\t{}\t{}\t{}",
+            bar.index, bar.numerator, bar.denominator
+        )
+        .unwrap();
+    }
+
+    for bpm in &chart.bpm {
+        let tick = layout.convert(bpm.beginning_measure * chart.resolution + bpm.offset);
+        writeln!(out, "@BPM\t{}\t{:.6}", layout.position(tick), bpm.bpm).unwrap();
+    }
+
+    let mut slides: Vec<Vec<&NoteType>> = vec![];
+
+    for note in &chart.notes {
+        let tick = layout.convert(note.tick(chart.resolution));
+        let cell_width = format!("{}{}", base36(note.cell()), base36(note.width()));
+        let position = layout.position(tick);
+
+        match note {
+            NoteType::Tap(_) => writeln!(out, "#{}:t{}", position, cell_width).unwrap(),
+            NoteType::ExTap(note) => writeln!(
+                out,
+                "#{}:x{}{}",
+                position,
+                cell_width,
+                animation(&note.animation)
+            )
+            .unwrap(),
+            NoteType::Flick(_) => writeln!(out, "#{}:f{}A", position, cell_width).unwrap(),
+            NoteType::Mine(_) => writeln!(out, "#{}:d{}", position, cell_width).unwrap(),
+            NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => {
+                if let Some(animation) = &note.animation {
+                    writeln!(
+                        out,
+                        "#{}:x{}{}",
+                        position,
+                        cell_width,
+                        self::animation(animation)
+                    )
+                    .unwrap();
+                }
+
+                writeln!(out, "#{}:h{}", position, cell_width).unwrap();
+                writeln!(out, "#{}>s", layout.convert(note.duration)).unwrap();
+            }
+            NoteType::Slide(_)
+            | NoteType::SlideControlPoint(_)
+            | NoteType::SlideWithExTapHead(_)
+            | NoteType::SlideControlPointWithExTapHead(_) => {
+                let chain = slides.iter_mut().find(|chain| {
+                    let (end_tick, end_cell, end_width) = slide_end(chain.last().unwrap(), chart);
+                    end_tick == note.tick(chart.resolution)
+                        && end_cell == note.cell()
+                        && end_width == note.width()
+                });
+
+                match chain {
+                    Some(chain) => chain.push(note),
+                    None => slides.push(vec![note]),
+                }
+            }
+            NoteType::Air(_) => writeln!(out, "#{}:a{}UCN", position, cell_width).unwrap(),
+            NoteType::AirUpRight(_) => writeln!(out, "#{}:a{}URN", position, cell_width).unwrap(),
+            NoteType::AirUpLeft(_) => writeln!(out, "#{}:a{}ULN", position, cell_width).unwrap(),
+            NoteType::AirDown(_) => writeln!(out, "#{}:a{}DCN", position, cell_width).unwrap(),
+            NoteType::AirDownRight(_) => writeln!(out, "#{}:a{}DRN", position, cell_width).unwrap(),
+            NoteType::AirDownLeft(_) => writeln!(out, "#{}:a{}DLN", position, cell_width).unwrap(),
+            NoteType::AirHold(_)
+            | NoteType::AirSlide(_)
+            | NoteType::AirSlideControlPoint(_)
+            | NoteType::AirCrush(_) => {}
+        }
+    }
+
+    for chain in slides {
+        let head = chain[0];
+        let start = head.tick(chart.resolution);
+        let position = layout.position(layout.convert(start));
+
+        if let NoteType::SlideWithExTapHead(note) | NoteType::SlideControlPointWithExTapHead(note) =
+            head
+        {
+            writeln!(
+                out,
+                "#{}:x{}{}{}",
+                position,
+                base36(note.cell),
+                base36(note.width),
+                animation(note.animation.as_deref().unwrap_or_default())
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            out,
+            "#{}:s{}{}",
+            position,
+            base36(head.cell()),
+            base36(head.width())
+        )
+        .unwrap();
+
+        for segment in chain {
+            let (end_tick, end_cell, end_width) = slide_end(segment, chart);
+            // the type of a segment decides whether the point it ends on is
+            // judged, or is only used to shape the slide.
+            let kind = match segment {
+                NoteType::SlideControlPoint(_) | NoteType::SlideControlPointWithExTapHead(_) => 'c',
+                _ => 's',
+            };
+
+            writeln!(
+                out,
+                "#{}>{}{}{}",
+                layout.convert(end_tick - start),
+                kind,
+                base36(end_cell),
+                base36(end_width)
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+/// Returns the position, cell and width a slide segment ends at.
+fn slide_end(note: &NoteType, chart: &ChuniChart) -> (usize, usize, usize) {
+    match note {
+        NoteType::Slide(slide)
+        | NoteType::SlideControlPoint(slide)
+        | NoteType::SlideWithExTapHead(slide)
+        | NoteType::SlideControlPointWithExTapHead(slide) => (
+            note.tick(chart.resolution) + slide.duration,
+            slide.end_cell,
+            slide.end_width,
+        ),
+        _ => unreachable!("slide chain contains a note that is not a slide. bailing."),
+    }
+}
+
+fn animation(animation: &str) -> char {
+    match animation {
+        "UP" => 'U',
+        "DW" => 'D',
+        "CE" => 'C',
+        "LS" => 'L',
+        "RS" => 'R',
+        "LC" => 'A',
+        "RC" => 'W',
+        "BS" => 'I',
+        _ => 'U',
+    }
+}
+
+/// The notes of `chart` with a cell or width too large to be written by
+/// [`to_ugc`], which are clamped to fit instead.
+pub fn clamped(chart: &ChuniChart) -> Vec<usize> {
+    chart
+        .notes
+        .iter()
+        .enumerate()
+        .filter(|(_, note)| {
+            let (end_cell, end_width) = note.end().unwrap_or_default();
+            [note.cell(), note.width(), end_cell, end_width]
+                .iter()
+                .any(|x| *x >= 36)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn base36(value: usize) -> char {
+    char::from_digit(value.min(35) as u32, 36)
+        .unwrap()
+        .to_ascii_uppercase()
+}
+
+/// A run of bars sharing the same time signature.
+struct Bar {
+    /// The index of the first bar in the run.
+    index: usize,
+    /// The position of the first bar in the run, in UMIGURI ticks.
+    tick: usize,
+    numerator: usize,
+    denominator: usize,
+}
+
+impl Bar {
+    /// The length of a bar, in UMIGURI ticks. Signatures too short for a
+    /// single tick still take one, so positions can always be divided by it.
+    fn length(&self) -> usize {
+        (TICKS * 4 * self.numerator / self.denominator.max(1)).max(1)
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+/// UMIGURI charts position notes by bar and beat, whereas measures in `.c2s`
+/// files always have the same length, and time signatures only change where
+/// bar lines are drawn. This maps positions between the two.
+struct BarLayout {
+    resolution: usize,
+    bars: Vec<Bar>,
+}
+
+impl BarLayout {
+    fn new(chart: &ChuniChart) -> BarLayout {
+        let mut layout = BarLayout {
+            resolution: chart.resolution,
+            bars: vec![Bar {
+                index: 0,
+                tick: 0,
+                numerator: chart.met_def.signature.numerator.max(1),
+                denominator: chart.met_def.signature.denominator.max(1),
+            }],
+        };
+
+        // the designations have to be in order, as each one is placed
+        // relative to the one before it.
+        for (tick, signature) in chart.time_signatures() {
+            let tick = layout.convert(tick);
+            let last = layout.bars.last().unwrap();
+            let elapsed = tick - last.tick;
+            let mut index = last.index + elapsed / last.length();
+            let remainder = elapsed % last.length();
+
+            // a time signature change in the middle of a bar cuts the bar
+            // short, which we can only express as a bar of its own, with a
+            // signature of exactly its length.
+            if remainder != 0 {
+                let whole = TICKS * 4;
+                let divisor = gcd(remainder, whole);
+                layout.bars.push(Bar {
+                    index,
+                    tick: tick - remainder,
+                    numerator: remainder / divisor,
+                    denominator: whole / divisor,
+                });
+                index += 1;
+            }
+
+            let bar = Bar {
+                index,
+                tick,
+                numerator: signature.numerator.max(1),
+                denominator: signature.denominator.max(1),
+            };
+
+            match layout.bars.last_mut() {
+                Some(last) if last.index == bar.index => *last = bar,
+                _ => layout.bars.push(bar),
+            }
+        }
+
+        layout
+    }
+
+    /// Converts a length in `.c2s` ticks to UMIGURI ticks.
+    fn convert(&self, tick: usize) -> usize {
+        tick * TICKS * 4 / self.resolution
+    }
+
+    /// Formats a position in UMIGURI ticks as `bar'tick`.
+    fn position(&self, tick: usize) -> String {
+        let bar = self.bars.iter().rev().find(|bar| bar.tick <= tick).unwrap();
+        let elapsed = tick - bar.tick;
+
+        format!(
+            "{}'{}",
+            bar.index + elapsed / bar.length(),
+            elapsed % bar.length()
+        )
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    string::ParseError,
+};
+
+use crate::{
+    json::{Json, ToJson},
+    note::{ExTap, NoteType},
+    ChuniChart,
+};
+
+/// A version of the `.c2s` format, such as `1.12.00`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: usize,
+    pub minor: usize,
+    pub patch: usize,
+}
+
+impl Version {
+    /// The version written by CHUNITHM LUMINOUS, the latest one known.
+    pub const LUMINOUS: Version = Version::new(1, 12, 0);
+
+    pub const fn new(major: usize, minor: usize, patch: usize) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut data = s.trim().split('.');
+
+        let major = data.next().unwrap().parse::<usize>().unwrap();
+        let minor = data.next().unwrap().parse::<usize>().unwrap();
+        let patch = data.next().unwrap().parse::<usize>().unwrap();
+
+        Ok(Version {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}.{:02}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The two columns of the `VERSION` line of a `.c2s` file. The game writes
+/// the same version to both of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChartVersion {
+    pub first: Version,
+    pub second: Version,
+}
+
+impl ChartVersion {
+    pub const LUMINOUS: ChartVersion = ChartVersion::new(Version::LUMINOUS);
+
+    /// A chart version with `version` in both columns.
+    pub const fn new(version: Version) -> ChartVersion {
+        ChartVersion {
+            first: version,
+            second: version,
+        }
+    }
+
+    /// Whether or not `HLD`, `HXD` and slide notes can end with an
+    /// animation, and air traces and air slides with a color. These fields
+    /// were added in LUMINOUS, and are not read from older charts.
+    pub fn has_trailing_fields(&self) -> bool {
+        self.first >= Version::LUMINOUS
+    }
+
+    /// Whether or not holds and slides can start with an ExTap, as `HXD`,
+    /// `SXD` and `SXC` notes. Older charts place a `CHR` on top of the start
+    /// of the note instead.
+    pub fn has_ex_tap_heads(&self) -> bool {
+        self.first >= Version::LUMINOUS
+    }
+}
+
+impl Default for ChartVersion {
+    /// The latest known version, which charts without a `VERSION` line are
+    /// parsed as.
+    fn default() -> Self {
+        ChartVersion::LUMINOUS
+    }
+}
+
+impl FromStr for ChartVersion {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, data) = s.trim().split_once('\t').unwrap();
+        let mut data = data.trim().split('\t');
+
+        let first = data.next().unwrap().parse::<Version>().unwrap();
+        let second = match data.next() {
+            Some(second) => second.parse::<Version>().unwrap(),
+            None => first,
+        };
+
+        Ok(ChartVersion { first, second })
+    }
+}
+
+impl fmt::Display for ChartVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VERSION\t{}\t{}", self.first, self.second)
+    }
+}
+
+/// Something in a chart that the version it was converted to with
+/// [`ChuniChart::convert_version`] can't represent, and was left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionIssue {
+    /// The measure of the note that was changed.
+    pub measure: usize,
+    /// The offset of the note that was changed.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for VersionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (measure {}, offset {})",
+            self.message, self.measure, self.offset
+        )
+    }
+}
+
+impl ToJson for VersionIssue {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("measure", self.measure.into()),
+            ("offset", self.offset.into()),
+            ("message", self.message.as_str().into()),
+        ])
+    }
+}
+
+impl ChuniChart {
+    /// Rewrites the chart for the game running `version`, returning
+    /// everything that had to be left out.
+    ///
+    /// Going to a version without ExTap heads splits every `HXD`, and every
+    /// `SXD` and `SXC` starting a slide chain, into a `CHR` and a `HLD`,
+    /// `SLD` or `SLC` at the same place, while going from one to a version
+    /// with them merges those pairs back. The rest of an ExTap-headed chain
+    /// follows its head.
+    /// Trailing animations and colors are dropped for versions without them.
+    pub fn convert_version(&mut self, version: ChartVersion) -> Vec<VersionIssue> {
+        if !version.has_ex_tap_heads() {
+            self.split_ex_tap_heads();
+        } else if !self.version.has_ex_tap_heads() {
+            self.merge_ex_tap_heads();
+        }
+
+        let mut issues = vec![];
+        if !version.has_trailing_fields() {
+            for note in self.notes.iter_mut() {
+                let (measure, offset, tag) = (note.measure(), note.offset(), note.tag());
+                let mut issue = |message: String| {
+                    issues.push(VersionIssue {
+                        measure,
+                        offset,
+                        message,
+                    })
+                };
+
+                match note {
+                    NoteType::Hold(note) | NoteType::HoldWithExTapHead(note) => {
+                        if let Some(animation) = note.animation.take() {
+                            issue(format!("animation {} of {} dropped", animation, tag));
+                        }
+                    }
+                    NoteType::Slide(note)
+                    | NoteType::SlideControlPoint(note)
+                    | NoteType::SlideWithExTapHead(note)
+                    | NoteType::SlideControlPointWithExTapHead(note) => {
+                        if let Some(animation) = note.animation.take() {
+                            issue(format!("animation {} of {} dropped", animation, tag));
+                        }
+                    }
+                    NoteType::Air(note)
+                    | NoteType::AirUpRight(note)
+                    | NoteType::AirUpLeft(note)
+                    | NoteType::AirDown(note)
+                    | NoteType::AirDownRight(note)
+                    | NoteType::AirDownLeft(note)
+                        if note.color != "DEF" =>
+                    {
+                        let color = std::mem::replace(&mut note.color, "DEF".to_string());
+                        issue(format!("color {} of {} dropped", color, tag));
+                    }
+                    NoteType::AirHold(note) if note.color != "DEF" => {
+                        let color = std::mem::replace(&mut note.color, "DEF".to_string());
+                        issue(format!("color {} of {} dropped", color, tag));
+                    }
+                    NoteType::AirCrush(note) if note.color != "DEF" => {
+                        let color = std::mem::replace(&mut note.color, "DEF".to_string());
+                        issue(format!("color {} of {} dropped", color, tag));
+                    }
+                    NoteType::AirSlide(note) | NoteType::AirSlideControlPoint(note)
+                        if note.color != "DEF" =>
+                    {
+                        let color = std::mem::replace(&mut note.color, "DEF".to_string());
+                        issue(format!("color {} of {} dropped", color, tag));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.version = version;
+        issues
+    }
+
+    /// Splits ExTap-headed holds and slides into a `CHR` followed by the
+    /// plain note. The animation of the head goes to the `CHR`, and notes
+    /// starting at the same place share one. Slide segments continuing a
+    /// chain only lose their ExTap head, as it is the one of the chain.
+    fn split_ex_tap_heads(&mut self) {
+        let ends = self.slide_ends();
+        let mut heads = HashSet::new();
+        for note in std::mem::take(&mut self.notes) {
+            let joint = (note.tick(self.resolution), note.cell(), note.width());
+            let (animation, note) = match note {
+                NoteType::HoldWithExTapHead(mut note) => {
+                    (note.animation.take(), NoteType::Hold(note))
+                }
+                NoteType::SlideWithExTapHead(mut note) => {
+                    (note.animation.take(), NoteType::Slide(note))
+                }
+                NoteType::SlideControlPointWithExTapHead(mut note) => {
+                    (note.animation.take(), NoteType::SlideControlPoint(note))
+                }
+                note => {
+                    self.notes.push(note);
+                    continue;
+                }
+            };
+
+            let key = (note.measure(), note.offset(), note.cell(), note.width());
+            if !(note.slide().is_some() && ends.contains(&joint)) && heads.insert(key) {
+                self.notes.push(NoteType::ExTap(ExTap {
+                    measure: note.measure(),
+                    offset: note.offset(),
+                    cell: note.cell(),
+                    width: note.width(),
+                    animation: animation.unwrap_or_else(|| "UP".to_string()),
+                }));
+            }
+            self.notes.push(note);
+        }
+    }
+
+    /// Merges every `CHR` placed on top of the start of holds or slide
+    /// chains into them, as the matching ExTap-headed notes. The segments
+    /// continuing an ExTap-headed chain become ExTap-headed too, while a
+    /// `CHR` on one of their joints stays a note of its own.
+    fn merge_ex_tap_heads(&mut self) {
+        let key = |note: &NoteType| (note.measure(), note.offset(), note.cell(), note.width());
+
+        let mut heads = HashMap::new();
+        for (i, note) in self.notes.iter().enumerate() {
+            if let NoteType::ExTap(head) = note {
+                heads
+                    .entry(key(note))
+                    .or_insert((i, head.animation.clone()));
+            }
+        }
+
+        let ends = self.slide_ends();
+        let mut order = (0..self.notes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| self.notes[*i].tick(self.resolution));
+
+        // the ends of ExTap-headed slide segments, with their animation.
+        let mut chains: HashMap<_, String> = HashMap::new();
+        let mut merged = HashSet::new();
+        for i in order {
+            let note = &mut self.notes[i];
+            let start = (note.tick(self.resolution), note.cell(), note.width());
+            let joint = note.slide().is_some() && ends.contains(&start);
+            let head = match joint {
+                true => chains
+                    .get(&start)
+                    .map(|animation| (None, animation.clone())),
+                false => heads
+                    .get(&key(note))
+                    .map(|(i, animation)| (Some(*i), animation.clone())),
+            };
+            let Some((head, animation)) = head else {
+                continue;
+            };
+
+            let merged_note = match note {
+                NoteType::Hold(hold) if hold.animation.is_none() => {
+                    hold.animation = Some(animation);
+                    NoteType::HoldWithExTapHead(hold.clone())
+                }
+                NoteType::Slide(slide) if slide.animation.is_none() => {
+                    slide.animation = Some(animation);
+                    NoteType::SlideWithExTapHead(slide.clone())
+                }
+                NoteType::SlideControlPoint(slide) if slide.animation.is_none() => {
+                    slide.animation = Some(animation);
+                    NoteType::SlideControlPointWithExTapHead(slide.clone())
+                }
+                _ => continue,
+            };
+
+            if let Some(slide) = merged_note.slide() {
+                let end = (start.0 + slide.duration, slide.end_cell, slide.end_width);
+                chains.insert(end, slide.animation.clone().unwrap_or_default());
+            }
+            *note = merged_note;
+            merged.extend(head);
+        }
+
+        let mut i = 0;
+        self.notes.retain(|_| {
+            i += 1;
+            !merged.contains(&(i - 1))
+        });
+    }
+}
@@ -1,6 +1,25 @@
-use std::fs;
+use std::{collections::HashSet, fs, io::BufReader};
 
-use nai_rs::ChuniChart;
+use nai_rs::{
+    autoplay::AirInput,
+    click::{self, ClickOptions},
+    difficulty::{Difficulty, Features, LevelEstimator},
+    events::{C2sEvent, C2sEvents, Extra, ExtraLine, Header},
+    format,
+    gimmicks::{GimmickKind, GimmickOptions, GimmickReport},
+    hands::Hand,
+    judge::{InputEvent, Judgement, TimingWindows},
+    library::Library,
+    lint::{Severity, Source},
+    met::TimeSignature,
+    music_xml::MusicInfo,
+    note::NoteType,
+    patterns::PatternKind,
+    score::{self, JudgementCounts, Lamp, Rank},
+    transform, ugc,
+    version::{ChartVersion, Version},
+    ChuniChart,
+};
 
 #[test]
 fn test_parse() {
@@ -12,3 +31,980 @@ fn test_parse() {
         Err(e) => panic!("{}", e),
     }
 }
+
+#[test]
+fn test_music_xml() {
+    let music_string: String = fs::read_to_string("tests/Music.xml").unwrap();
+    let music = music_string.parse::<MusicInfo>().unwrap();
+
+    assert_eq!(music.id, 2338);
+    assert_eq!(music.title, "ZEGALLTA & test");
+    assert_eq!(music.artist, "CODE:Emperor");
+    assert_eq!(music.genres, vec!["ORIGINAL"]);
+    assert_eq!(music.jacket, "CHU_UI_Jacket_2338.dds");
+    assert_eq!(music.cue_name, "music2338");
+    assert_eq!(music.fumens.len(), 3);
+    assert!(!music.fumens[2].enabled);
+
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let mut chart = ChuniChart::parse(chart_string).unwrap();
+    let fumen = music.attach(&mut chart, "music2338/2338_03.c2s").unwrap();
+
+    assert_eq!(fumen.difficulty_name, "MASTER");
+    assert_eq!(fumen.kind(), Some(Difficulty::Master));
+    assert_eq!(music.fumens[0].kind(), Some(Difficulty::Basic));
+    assert_eq!(chart.music, 2338);
+    assert_eq!(chart.difficult, 3);
+    assert_eq!(chart.level, 14.8);
+
+    let music = "<MusicData><!-- <name> -> --><name><id>7</id>\
+                 <str><![CDATA[a > b]]></str></name></MusicData>"
+        .parse::<MusicInfo>()
+        .unwrap();
+    assert_eq!(music.id, 7);
+    assert_eq!(music.title, "a > b");
+
+    // malformed documents read as far as they go instead of panicking.
+    let music = "<MusicData><name><id>x</id><str>t</name></oops><artistName"
+        .parse::<MusicInfo>()
+        .unwrap();
+    assert_eq!(music.id, 0);
+    assert_eq!(music.title, "t");
+    assert_eq!("".parse::<MusicInfo>().unwrap(), MusicInfo::default());
+}
+
+#[test]
+fn test_difficulty() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let mut chart = ChuniChart::parse(chart_string).unwrap();
+
+    // DIFFICULT is left at 00 in the file, which hides the creator.
+    assert_eq!(chart.difficulty(), Some(Difficulty::Basic));
+    assert_eq!(chart.displayed_creator(), None);
+
+    chart.difficult = 3;
+    assert_eq!(chart.difficulty(), Some(Difficulty::Master));
+    assert_eq!(chart.displayed_creator(), Some("CODE:Emperor"));
+
+    chart.difficult = 9;
+    assert_eq!(chart.difficulty(), None);
+    assert_eq!(chart.displayed_creator(), None);
+
+    for id in 0..6 {
+        let difficulty = Difficulty::from_id(id).unwrap();
+        assert_eq!(difficulty.id(), id);
+        assert_eq!(
+            difficulty.to_string().parse::<Difficulty>().unwrap(),
+            difficulty
+        );
+        assert_eq!(difficulty.shows_creator(), id >= 2);
+    }
+    assert_eq!(Difficulty::WorldsEnd.to_string(), "WORLD'S END");
+}
+
+/// A temporary directory, removed when dropped.
+struct TempDir(std::path::PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_library() {
+    let root = TempDir(std::env::temp_dir().join(format!("nai-rs-library-{}", std::process::id())));
+    let root = &root.0;
+    let song = root.join("music").join("music2338");
+    let broken = root.join("music").join("music9999");
+    fs::create_dir_all(&song).unwrap();
+    fs::create_dir_all(&broken).unwrap();
+    fs::copy("tests/Music.xml", song.join("Music.xml")).unwrap();
+    fs::copy("tests/zegallta_master.c2s", song.join("2338_03.c2s")).unwrap();
+    fs::write(broken.join("9999_03.c2s"), "TAP\tnot a number\n").unwrap();
+
+    let library = Library::scan(root).unwrap();
+    assert_eq!(library.songs.len(), 2);
+    assert!(library.charts().all(|entry| !entry.is_loaded()));
+
+    library.load();
+
+    let chart = library.songs[0].charts[0].chart().unwrap();
+    assert_eq!(chart.difficult, 3);
+    assert_eq!(chart.level, 14.8);
+    assert!(library.songs[1].music.is_err());
+    assert!(library.songs[1].charts[0].chart().is_err());
+    assert_eq!(library.errors().len(), 2);
+
+    // the songs share their Music.xml with their charts.
+    let music = library.songs[0].music.as_ref().unwrap();
+    assert_eq!(std::sync::Arc::strong_count(music), 2);
+}
+
+#[test]
+fn test_round_trip() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let written = ChuniChart::parse(chart.to_string()).unwrap();
+
+    assert_eq!(chart, written);
+}
+
+#[test]
+fn test_extra_lines() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string.clone()).unwrap();
+
+    // the T_* footer is written back exactly as it was read.
+    assert_eq!(chart.extra.len(), 69);
+    assert!(chart
+        .extra
+        .iter()
+        .all(|extra| extra.position == chart.notes.len() + 11));
+    assert_eq!(chart.to_string(), chart_string);
+
+    // unknown tags stay where they were, between the lines around them.
+    let input = chart_string.replacen("TAP\t1\t288", "FOO\t1\t2\nTAP\t1\t288", 1);
+    let chart = ChuniChart::parse(input.clone()).unwrap();
+    assert_eq!(
+        chart.extra[0],
+        Extra {
+            position: 12,
+            header: false,
+            line: ExtraLine::Unknown("FOO\t1\t2".to_string()),
+        }
+    );
+    assert_eq!(chart.to_string(), input);
+
+    // so do the ones among the header lines.
+    let input = chart_string.replacen("SEQUENCEID", "FOO\tbar\nSEQUENCEID", 1);
+    let chart = ChuniChart::parse(input.clone()).unwrap();
+    assert!(chart.extra[0].header);
+    assert_eq!(chart.extra[0].position, 2);
+    assert_eq!(chart.to_string(), input);
+
+    // fields that are the same in every known chart are kept too, along with
+    // air heights of any precision.
+    let edits = [
+        ("AIR\t", "DEF", "PPL"),
+        ("AHD\t", "DEF", "RED"),
+        ("SLD\t", "\tSLD", "\tSLC"),
+        ("ASD\t", "\t5.0\t", "\t1.25\t"),
+        ("ALD\t", "\t2.0\t", "\t0.125\t"),
+    ];
+    for (tag, from, to) in edits {
+        let line = chart_string.lines().find(|x| x.starts_with(tag)).unwrap();
+        let input = chart_string.replacen(line, &line.replacen(from, to, 1), 1);
+        assert_ne!(input, chart_string);
+        assert_eq!(ChuniChart::parse(input.clone()).unwrap().to_string(), input);
+    }
+}
+
+#[test]
+fn test_events() {
+    let file = fs::File::open("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::from_reader(BufReader::new(file)).unwrap();
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    assert_eq!(chart, ChuniChart::parse(chart_string).unwrap());
+
+    let file = fs::File::open("tests/zegallta_master.c2s").unwrap();
+    let events = C2sEvents::new(BufReader::new(file))
+        .skip_notes()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert!(events.contains(&C2sEvent::Header(Header::Creator(
+        "CODE:Emperor".to_string()
+    ))));
+    assert!(!events.iter().any(|x| matches!(x, C2sEvent::Note(_))));
+    let total = events.iter().find_map(|x| match x {
+        C2sEvent::Statistic(statistic) if statistic.name == "T_REC_ALL" => statistic.parse(),
+        _ => None,
+    });
+    assert_eq!(total, Some(2369));
+}
+
+#[test]
+fn test_parse_ref() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let borrowed = ChuniChart::parse_ref(&chart_string).unwrap();
+
+    assert_eq!(borrowed.creator, "CODE:Emperor");
+    let chart = ChuniChart::parse(chart_string.clone()).unwrap();
+    assert_eq!(borrowed.to_string(), chart.to_string());
+    assert_eq!(ChuniChart::from(borrowed), chart);
+}
+
+#[test]
+fn test_spans() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let (chart, spans) = ChuniChart::parse_with_spans(chart_string.clone()).unwrap();
+
+    assert_eq!(chart, ChuniChart::parse(chart_string.clone()).unwrap());
+    assert_eq!(spans.notes.len(), chart.notes.len());
+    assert_eq!(spans.bpm.len(), chart.bpm.len());
+    assert_eq!(spans.met.len(), chart.met.len());
+
+    for (note, span) in chart.notes.iter().zip(&spans.notes) {
+        let line = &chart_string[span.range.clone()];
+        assert_eq!(
+            chart_string.lines().nth(span.line - 1).unwrap().trim(),
+            line
+        );
+        assert!(line.starts_with(note.tag()));
+    }
+
+    let lint = &chart.lint()[0];
+    let span = spans.get(lint.source).unwrap();
+    // the second of two identical slides on lines 1291 and 1292.
+    assert_eq!(span.line, 1292);
+
+    // lints point to their own line, even when other lines share its place.
+    let chart_string = "VERSION\t1.12.00\t1.12.00\n\
+                        BPM_DEF\t120.000\t120.000\t120.000\t120.000\n\
+                        RESOLUTION\t384\n\
+                        BPM\t0\t0\t150.000\n\
+                        TAP\t0\t0\t0\t4\n\
+                        TAP\t0\t0\t20\t4\n";
+    let (chart, spans) = ChuniChart::parse_with_spans(chart_string.to_string()).unwrap();
+    let lines = chart
+        .lint()
+        .iter()
+        .map(|lint| (lint.source, spans.get(lint.source).unwrap().line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            (Source::Header("BPM_DEF"), 2),
+            (Source::Header("BPM_DEF"), 2),
+            (Source::Note(1), 6),
+        ]
+    );
+}
+
+#[test]
+fn test_versions() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    assert_eq!(chart.version, ChartVersion::LUMINOUS);
+    assert!(chart.to_string().starts_with("VERSION\t1.12.00\t1.12.00\n"));
+
+    // older charts have no trailing animations and colors.
+    let lines = "HLD\t1\t0\t0\t2\t192\tDW\nASD\t2\t0\t0\t2\tTAP\t5.0\t96\t4\t2\t5.0\n";
+    let older = format!("VERSION\t1.08.00\t1.08.00\n{}", lines);
+    let chart = ChuniChart::parse(older).unwrap();
+    assert_eq!(chart.version.first, Version::new(1, 8, 0));
+    assert!(!chart.version.has_trailing_fields());
+    match &chart.notes[..] {
+        [NoteType::Hold(hold), NoteType::AirSlide(slide)] => {
+            assert_eq!(hold.animation, None);
+            assert_eq!(slide.color, "DEF");
+        }
+        notes => panic!("unexpected notes: {:?}", notes),
+    }
+
+    let newer = format!(
+        "VERSION\t1.12.00\t1.12.00\n{}",
+        lines.replace("5.0\n", "5.0\tRED\n")
+    );
+    let chart = ChuniChart::parse(newer).unwrap();
+    assert_eq!(chart.notes[0].to_string(), "HLD\t1\t0\t0\t2\t192\tDW");
+}
+
+#[test]
+fn test_convert_version() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let older = ChartVersion::new(Version::new(1, 8, 0));
+
+    let mut converted = ChuniChart::parse(chart.to_string()).unwrap();
+    assert!(converted.convert_version(older).is_empty());
+    assert!(!converted.notes.iter().any(|note| matches!(
+        note,
+        NoteType::HoldWithExTapHead(_)
+            | NoteType::SlideWithExTapHead(_)
+            | NoteType::SlideControlPointWithExTapHead(_)
+    )));
+
+    // the CHR and HLD/SLD/SLC pairs are merged back when upgrading.
+    let mut converted = ChuniChart::parse(converted.to_string()).unwrap();
+    assert_eq!(converted.version, older);
+    assert!(converted.convert_version(ChartVersion::LUMINOUS).is_empty());
+    assert_eq!(converted, chart);
+
+    // a CHR on the joint of a slide chain stays a note of its own, while the
+    // rest of the chain follows the head.
+    let notes = "CHR\t2\t0\t4\t2\tUP\n\
+                 SLD\t2\t0\t4\t2\t96\t6\t2\tSLD\n\
+                 CHR\t2\t96\t6\t2\tCE\n\
+                 SLD\t2\t96\t6\t2\t96\t8\t2\tSLD\n";
+    let mut chart = ChuniChart::parse(format!("VERSION\t1.08.00\t1.08.00\n{}", notes)).unwrap();
+    assert!(chart.convert_version(ChartVersion::LUMINOUS).is_empty());
+    assert_eq!(
+        chart.notes.iter().map(|x| x.tag()).collect::<Vec<_>>(),
+        ["SXD", "CHR", "SXD"]
+    );
+    assert!(chart.convert_version(older).is_empty());
+    assert!(chart.to_string().contains(notes));
+
+    let mut chart =
+        ChuniChart::parse("ASD\t2\t0\t0\t2\tTAP\t5.0\t96\t4\t2\t5.0\tRED\n".to_string()).unwrap();
+    let issues = chart.convert_version(older);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].message, "color RED of ASD dropped");
+    assert!(chart
+        .to_string()
+        .ends_with("ASD\t2\t0\t0\t2\tTAP\t5.0\t96\t4\t2\t5.0\n"));
+
+    // so are the colors of air notes and AirHolds, even the default ones.
+    let notes = "AIR\t2\t0\t0\t2\tTAP\tDEF\nAHD\t2\t0\t4\t2\tTAP\t96\tRED\n";
+    let mut chart = ChuniChart::parse(notes.to_string()).unwrap();
+    let issues = chart.convert_version(older);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].message, "color RED of AHD dropped");
+    assert!(chart
+        .to_string()
+        .ends_with("AIR\t2\t0\t0\t2\tTAP\nAHD\t2\t0\t4\t2\tTAP\t96\n"));
+}
+
+#[test]
+fn test_time_signatures() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+
+    // MET 45 192 4 7
+    let met = &chart.met[4];
+    assert_eq!((met.beginning_measure, met.offset), (45, 192));
+    assert_eq!(met.signature.to_string(), "7/4");
+    assert_eq!(met.signature.bar_length(chart.resolution), 672);
+    assert_eq!(met.signature.beat_length(chart.resolution), 96);
+    assert_eq!(met.to_string(), "MET\t45\t192\t4\t7");
+    assert_eq!(chart.time_signature(50 * 384), met.signature);
+    assert_eq!(chart.met_def.signature, TimeSignature::COMMON);
+
+    // every time signature lasts a whole number of bars until the next one.
+    let signatures = chart.time_signatures();
+    for pair in signatures.windows(2) {
+        let ((start, signature), (end, _)) = (pair[0], pair[1]);
+        assert_eq!((end - start) % signature.bar_length(chart.resolution), 0);
+    }
+
+    assert_eq!(chart.expected_bpm_def(), chart.bpm_def);
+    assert_eq!(chart.bpm_def.mode, 234.0);
+}
+
+#[test]
+fn test_ugc() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let ugc = ugc::to_ugc(&ChuniChart::parse(chart_string.clone()).unwrap());
+    assert!(ugc.contains("@BEAT\t45\t7\t4\n"));
+
+    // a change half way through a 4/4 bar leaves a bar of 1/2, and empty
+    // time signatures still take up a tick.
+    let chart_string = chart_string
+        .replacen("MET\t45\t192\t4\t7", "MET\t45\t0\t4\t7", 1)
+        .replacen("MET\t138\t0\t4\t3", "MET\t138\t0\t4\t0", 1);
+    let ugc = ugc::to_ugc(&ChuniChart::parse(chart_string).unwrap());
+    assert!(ugc.contains("@BEAT\t44\t1\t2\n@BEAT\t45\t7\t4\n"));
+
+    // time signatures are laid out in order, whatever order they are written
+    // in, and cells past the last base 36 digit are clamped.
+    let mut chart = ChuniChart::parse(
+        "RESOLUTION\t384\nMET\t10\t0\t4\t3\nMET\t2\t0\t4\t2\nTAP\t0\t0\t40\t4\n".to_string(),
+    )
+    .unwrap();
+    let ugc = ugc::to_ugc(&chart);
+    assert!(ugc.contains("@BEAT\t0\t4\t4\n@BEAT\t2\t2\t4\n@BEAT\t18\t3\t4\n"));
+    assert!(ugc.contains("#0'0:tZ4\n"));
+    assert_eq!(ugc::clamped(&chart), vec![0]);
+    chart.notes[0] = "TAP\t0\t0\t4\t4".parse().unwrap();
+    assert!(ugc::clamped(&chart).is_empty());
+}
+
+#[test]
+fn test_statistics() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let stats = chart.statistics();
+
+    // values from the T_REC_* and T_*_RES lines of the footer.
+    assert_eq!(stats.taps, 910);
+    assert_eq!(stats.ex_taps, 379);
+    assert_eq!(stats.flicks, 200);
+    assert_eq!(stats.mines, 0);
+    assert_eq!(stats.holds, 91);
+    assert_eq!(stats.slides, 567);
+    assert_eq!(stats.airs, 198);
+    assert_eq!(stats.air_holds, 24);
+    assert_eq!(stats.total(), 2369);
+    assert_eq!(stats.first_tick, 624);
+    assert_eq!(stats.last_tick, 54720);
+}
+
+#[test]
+fn test_lint() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let lints = chart.lint();
+
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].severity, Severity::Warning);
+    assert_eq!((lints[0].measure, lints[0].offset), (79, 287));
+
+    // BPM_DEF only keeps 3 decimals.
+    let chart_string = chart
+        .to_string()
+        .replacen("BPM\t0\t0\t234.000", "BPM\t0\t0\t234.0004", 1);
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    assert_eq!(chart.bpm[0].bpm, 234.0004);
+    assert_eq!(chart.lint().len(), 1);
+}
+
+#[test]
+fn test_mirror() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let mut mirrored = ChuniChart::parse(chart.to_string()).unwrap();
+
+    assert!(transform::mirror(&mut mirrored).is_empty());
+    assert_ne!(chart, mirrored);
+    assert!(mirrored
+        .lint()
+        .iter()
+        .all(|lint| lint.severity != Severity::Error));
+
+    transform::mirror(&mut mirrored);
+    assert_eq!(chart, mirrored);
+
+    // notes past the last lane are reported instead of overflowing.
+    let mut chart = ChuniChart::parse("TAP\t2\t0\t14\t4\nTAP\t2\t0\t0\t4\n".to_string()).unwrap();
+    assert_eq!(transform::mirror(&mut chart), [0]);
+    assert_eq!(chart.notes[0].cell(), 0);
+    assert_eq!(chart.notes[1].cell(), 12);
+}
+
+#[test]
+fn test_random() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+
+    // whether or not every Slide and air note starts where another note
+    // ends, which is how slide chains and air notes stay together.
+    let linked = |chart: &ChuniChart| {
+        let ends = chart
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let (cell, width) = note.end().unwrap_or((note.cell(), note.width()));
+                Some((note.tick(chart.resolution) + note.duration()?, cell, width))
+            })
+            .collect::<HashSet<_>>();
+
+        chart
+            .notes
+            .iter()
+            .map(|note| {
+                let start = (note.tick(chart.resolution), note.cell(), note.width());
+                (note.is_air() || note.end().is_some()) && ends.contains(&start)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let modifiers: [fn(&mut ChuniChart, u64); 3] = [
+        transform::random,
+        transform::full_random,
+        transform::s_random,
+    ];
+    for modifier in modifiers {
+        let mut shuffled = ChuniChart::parse(chart.to_string()).unwrap();
+        modifier(&mut shuffled, 7);
+        assert_ne!(chart, shuffled);
+
+        let mut again = ChuniChart::parse(chart.to_string()).unwrap();
+        modifier(&mut again, 7);
+        assert_eq!(shuffled, again);
+
+        assert_eq!(chart.statistics(), shuffled.statistics());
+        assert!(shuffled
+            .lint()
+            .iter()
+            .all(|lint| lint.severity != Severity::Error));
+        for (before, after) in linked(&chart).iter().zip(linked(&shuffled)) {
+            assert!(!before || after);
+        }
+    }
+}
+
+#[test]
+fn test_format() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    assert_eq!(format::format(&chart_string).unwrap(), chart_string);
+
+    // hand-edited files can use spaces, other precisions and any measure
+    // order. notes sharing a position keep their relative order.
+    let (mut notes, rest): (Vec<&str>, Vec<&str>) = chart_string.lines().partition(|line| {
+        [
+            "TAP", "CHR", "SLD", "SLC", "SXD", "SXC", "AIR", "AUR", "AUL", "ADL",
+        ]
+        .iter()
+        .any(|tag| line.starts_with(tag))
+    });
+    notes.sort_by_key(|line| {
+        std::cmp::Reverse(line.split('\t').nth(1).unwrap().parse::<usize>().unwrap())
+    });
+    let messy = rest
+        .iter()
+        .chain(notes.iter())
+        .map(|line| line.replace('\t', "  ").replace("234.000", "234"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    assert_eq!(format::format(&messy).unwrap(), chart_string);
+
+    // free text keeps its spaces.
+    let spaced = chart_string
+        .replace("CODE:Emperor", "Foo  Bar")
+        .replace("T_REC_TAP\t910", "T_NOTE\tsome  text");
+    let formatted = format::format(&spaced.replace("CREATOR\t", "CREATOR ")).unwrap();
+    assert_eq!(formatted, spaced);
+    assert_eq!(ChuniChart::parse(formatted).unwrap().creator, "Foo  Bar");
+
+    // unknown lines stay between the notes they were read between.
+    let chart_string =
+        "RESOLUTION\t384\n\nTAP\t0\t0\t0\t4\nFOO\tbar\nTAP\t0\t192\t4\t4\n\nT_REC_TAP\t2\n";
+    let chart = ChuniChart::parse(chart_string.to_string()).unwrap();
+    let written = chart.to_string();
+    assert!(written.contains("TAP\t0\t0\t0\t4\nFOO\tbar\nTAP\t0\t192\t4\t4\n\nT_REC_TAP\t2\n"));
+    assert_eq!(format::format(&written).unwrap(), written);
+    assert_eq!(
+        format::format(&chart_string.replace("T_REC_TAP\t2\n", "")).unwrap(),
+        written.replace("\nT_REC_TAP\t2\n\n", "")
+    );
+}
+
+#[test]
+fn test_timing() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let tempo = chart.tempo_map();
+
+    // values from the T_FIRST_* and T_FINAL_* lines of the footer.
+    assert_eq!(tempo.ms(624).floor(), 1666.0);
+    assert_eq!(tempo.ms(54720).floor(), 146256.0);
+    assert_eq!(tempo.tick(tempo.ms(54720)).round(), 54720.0);
+    assert_eq!(tempo.bpm(130 * 384 + 192), 238.0);
+}
+
+#[test]
+fn test_scroll() {
+    let chart_string = [
+        "RESOLUTION\t384",
+        "BPM\t0\t0\t120.000",
+        "SFL\t1\t0\t192\t2.000000",
+        "SFL\t2\t0\t96\t0.000000",
+        "SFL\t3\t0\t96\t-1.000000",
+        "TAP\t4\t0\t0\t4",
+    ]
+    .join("\n");
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    assert_eq!(chart.sfl.len(), 3);
+    assert_eq!(chart.sfl[0].duration, 192);
+    assert_eq!(chart.sfl[0].to_string(), "SFL\t1\t0\t192\t2.000000");
+
+    let scroll = chart.scroll_map();
+    assert_eq!(scroll.multiplier(0), 1.0);
+    assert_eq!(scroll.multiplier(400), 2.0);
+    assert_eq!(scroll.multiplier(800), 0.0);
+    assert_eq!(scroll.multiplier(1200), -1.0);
+    assert_eq!(scroll.multiplier(1300), 1.0);
+
+    // x2 for half a measure, stopped for a quarter and backwards for another.
+    assert_eq!(scroll.position(768.0), 960.0);
+    assert_eq!(scroll.position(864.0), 960.0);
+    assert_eq!(scroll.position(1248.0), 1152.0);
+    assert_eq!(scroll.distance(1536, 0.0), 1440.0);
+    assert_eq!(scroll.distance(1536, 2000.0).round(), 1056.0);
+}
+
+#[test]
+fn test_gimmicks() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    assert_eq!(
+        chart.gimmicks(&GimmickOptions::default()),
+        GimmickReport::default()
+    );
+
+    let chart_string = [
+        "RESOLUTION\t384",
+        "BPM\t0\t0\t120.000",
+        "BPM\t2\t0\t240.000",
+        "SFL\t2\t0\t384\t0.500000",
+        "SFL\t3\t0\t96\t0.000000",
+        "SFL\t3\t192\t96\t-1.000000",
+        "SFL\t5\t0\t384\t8.000000",
+        "TAP\t5\t192\t0\t4",
+        "TAP\t7\t0\t0\t4",
+    ]
+    .join("\n");
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let report = chart.gimmicks(&GimmickOptions::default());
+
+    let kinds = report.gimmicks.iter().map(|x| x.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            GimmickKind::SpeedChange,
+            GimmickKind::CancelledBpm,
+            GimmickKind::Stop,
+            GimmickKind::Reverse,
+            GimmickKind::SpeedChange,
+        ]
+    );
+    assert_eq!(report.gimmicks[1].measure, 2);
+    assert_eq!(report.gimmicks[2].end_tick, 3 * 384 + 96);
+
+    // the x8 part shows the first tap for 48 ticks at 240 BPM only.
+    assert_eq!(report.fast_notes.len(), 1);
+    assert_eq!(report.fast_notes[0].note, 0);
+    assert_eq!(report.fast_notes[0].approach_time.round(), 125.0);
+    assert_eq!(
+        chart.scroll_map().approach_time(7 * 384, 384.0).round(),
+        1000.0
+    );
+}
+
+#[test]
+fn test_density() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let density = chart.density(1000.0);
+
+    assert!(density.peak_nps >= density.average_nps);
+    assert_eq!(density.measures.len(), 142);
+
+    // judgements are approximated, so they are only close to T_JUDGE_ALL and
+    // T_PROG_00..95, within 1% overall and 10% per part.
+    let judgements = chart.judgement_ticks().len() as f64;
+    assert!((judgements - 2913.0).abs() <= 2913.0 * 0.01);
+
+    let footer = [
+        178, 141, 169, 127, 147, 124, 192, 144, 127, 125, 161, 110, 130, 176, 163, 147, 143, 173,
+        205, 167,
+    ];
+    for (progress, footer) in chart.progress().into_iter().zip(footer) {
+        assert!(progress.abs_diff(footer) * 10 <= footer);
+    }
+}
+
+#[test]
+fn test_click_track() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let options = ClickOptions {
+        sample_rate: 1000,
+        metronome: false,
+        ..Default::default()
+    };
+
+    // the first note is played at 1666ms, according to T_FIRST_MSEC.
+    let samples = click::click_track(&chart, &options);
+    let first = samples.iter().position(|x| *x != 0).unwrap();
+    assert!((1666..=1668).contains(&first));
+    assert_eq!(samples.len(), 147256);
+
+    let wav = click::to_wav(&chart, &options);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(wav.len(), 44 + samples.len() * 2);
+
+    // a slide chain clicks once, on its head, even when that is an SXC.
+    let chart = ChuniChart::parse(
+        "RESOLUTION\t384\n\
+         BPM\t0\t0\t120.000\n\
+         SLD\t0\t0\t0\t4\t384\t4\t4\tSLD\n\
+         SLC\t1\t0\t4\t4\t384\t8\t4\tSLD\n\
+         SLD\t2\t0\t8\t4\t384\t4\t4\tSLD\n\
+         SXC\t4\t0\t0\t4\t384\t4\t4\tSLD\tUP\n\
+         SXD\t5\t0\t4\t4\t384\t8\t4\tSLD\tUP\n"
+            .to_string(),
+    )
+    .unwrap();
+    let samples = click::click_track(&chart, &options);
+    let clicks = (0..samples.len())
+        .filter(|i| samples[*i] != 0 && samples[i.saturating_sub(100)..*i].iter().all(|x| *x == 0))
+        .collect::<Vec<_>>();
+    // the tap sound starts on a zero crossing.
+    assert_eq!(clicks, vec![1, 8000]);
+}
+
+#[test]
+fn test_autoplay() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let frames = chart.autoplay();
+
+    assert!(frames.windows(2).all(|x| x[0].end == x[1].start));
+    assert_eq!(frames[0].start, 0.0);
+    assert_eq!(frames.last().unwrap().end.floor(), 146256.0);
+
+    // TAP 1 0 8 2, then TAP 1 48 10 2.
+    assert_eq!(frames[1].start.floor(), 1666.0);
+    assert_eq!(frames[1].cells(), [8, 9]);
+    assert_eq!(frames[1].pads, 0b1111 << 16);
+    assert!(frames[2].cells().is_empty());
+    assert_eq!(frames[3].cells(), [10, 11]);
+
+    assert!(frames.iter().any(|x| x.air == AirInput::Rising));
+    assert!(frames.iter().any(|x| x.air == AirInput::Held));
+}
+
+#[test]
+fn test_judge() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let windows = TimingWindows::default();
+    let inputs = InputEvent::from_autoplay(&chart.autoplay());
+
+    let result = chart.judge(&inputs, &windows);
+    // every AirSlide control point is judged too.
+    assert_eq!(result.judgements.len(), chart.statistics().total() + 136);
+
+    // judgements are approximated, so they are only close to T_JUDGE_ALL.
+    assert_eq!(result.max_combo, 2884);
+    // the heads of HXD 2 0 5, SXC 2 0 7 and CHR 2 0 10.
+    let ticks = chart.judgement_ticks();
+    assert_eq!(ticks.iter().filter(|x| **x == 2 * 384).count(), 3);
+    assert_eq!(result.justice_critical, result.max_combo);
+    assert_eq!(result.score, 1010000);
+
+    // ExTaps stay critical when hit late, Taps do not.
+    let late = inputs
+        .iter()
+        .map(|x| InputEvent {
+            time: x.time + 50.0,
+            ..*x
+        })
+        .collect::<Vec<_>>();
+    let result = chart.judge(&late, &windows);
+    let judgement = |tag: &str| {
+        result
+            .judgements
+            .iter()
+            .find(|x| chart.notes[x.note].tag() == tag)
+            .unwrap()
+            .judgement
+    };
+    assert_eq!(judgement("TAP"), Judgement::Justice);
+    assert_eq!(judgement("CHR"), Judgement::JusticeCritical);
+    assert_eq!(result.miss, 0);
+    assert!(result.score < 1010000);
+
+    let result = chart.judge(&[], &windows);
+    assert_eq!(result.miss, result.max_combo);
+    assert_eq!(result.score, 0);
+
+    let log = inputs
+        .iter()
+        .map(|x| format!("{}\n", x))
+        .collect::<String>();
+    let parsed = InputEvent::parse_log(&log).unwrap();
+    assert_eq!(parsed.len(), inputs.len());
+    assert_eq!(chart.judge(&parsed, &windows).score, 1010000);
+}
+
+#[test]
+fn test_score() {
+    let counts = JudgementCounts {
+        justice_critical: 2000,
+        justice: 369,
+        attack: 0,
+        miss: 0,
+    };
+    assert_eq!(counts.score(2369), 1008442);
+    assert_eq!(counts.lamp(), Lamp::AllJustice);
+
+    assert_eq!(Rank::from_score(1010000), Rank::SSSPlus);
+    assert_eq!(Rank::from_score(1008442), Rank::SSS);
+    assert_eq!(Rank::from_score(975000).to_string(), "S");
+    assert_eq!(Rank::from_score(499999), Rank::D);
+
+    assert_eq!(score::rating(1010000, 14.8), 16.95);
+    assert_eq!(score::rating(1008000, 14.8), 16.85);
+    assert_eq!(score::rating(1003000, 14.8), 16.1);
+    assert_eq!(score::rating(975000, 14.8), 14.8);
+    assert_eq!(score::rating(950000, 14.8), 13.3);
+    assert_eq!(score::rating(400000, 14.8), 0.0);
+
+    assert_eq!(
+        score::over_power(1010000, 14.8, Lamp::AllJusticeCritical),
+        89.0
+    );
+    assert_eq!(score::over_power(1000000, 14.8, Lamp::FullCombo), 79.5);
+
+    let music_string: String = fs::read_to_string("tests/Music.xml").unwrap();
+    let music = music_string.parse::<MusicInfo>().unwrap();
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let mut chart = ChuniChart::parse(chart_string).unwrap();
+    music.attach(&mut chart, "music2338/2338_03.c2s").unwrap();
+
+    let inputs = InputEvent::from_autoplay(&chart.autoplay());
+    let result = chart.judge(&inputs, &TimingWindows::default());
+    let card = chart.score_card(&result.counts());
+    assert_eq!(card.score, 1010000);
+    assert_eq!(card.lamp, Lamp::AllJusticeCritical);
+    assert_eq!(card.rating, 16.95);
+    assert_eq!(card.over_power, 89.0);
+}
+
+#[test]
+fn test_features() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let features = chart.features();
+
+    // T_LEN_SLD is 78396ms in the footer.
+    assert!((features.slide_length - 78396.0).abs() < 100.0);
+    assert_eq!(features.flicks, 200);
+    assert!(features.air_ratio > 0.0 && features.air_ratio < 1.0);
+    assert!(features.simultaneous_rate > 0.0 && features.simultaneous_rate < 1.0);
+    assert!(features.hand_travel > 0.0 && features.hand_travel < 16.0);
+
+    // the chart is a 14.8 in Music.xml.
+    assert!((chart.estimate_level() - 14.8).abs() <= 1.0);
+
+    // slides and flicks are counted per minute.
+    let mut features = Features {
+        length: 60.0,
+        average_nps: 10.0,
+        peak_nps: 20.0,
+        slide_length: 10000.0,
+        air_ratio: 0.2,
+        flicks: 100,
+        hand_travel: 2.0,
+        simultaneous_rate: 0.4,
+    };
+    let estimator = LevelEstimator::default();
+    assert_eq!(estimator.estimate(&features), 10.5);
+    features.length = 120.0;
+    assert_eq!(estimator.estimate(&features), 10.1);
+
+    let estimator = LevelEstimator {
+        intercept: 100.0,
+        ..Default::default()
+    };
+    assert_eq!(estimator.estimate(&features), 15.9);
+}
+
+#[test]
+fn test_patterns() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let mut chart = ChuniChart::parse(chart_string).unwrap();
+
+    let notes = [
+        // a jack, then a trill on the same cell.
+        "TAP\t1\t0\t0\t2",
+        "TAP\t1\t48\t0\t2",
+        "TAP\t1\t96\t0\t2",
+        "TAP\t1\t144\t0\t2",
+        "TAP\t1\t192\t4\t2",
+        "TAP\t1\t240\t0\t2",
+        "TAP\t1\t288\t4\t2",
+        // stairs, far enough from the trill to not continue it.
+        "TAP\t3\t0\t0\t2",
+        "TAP\t3\t48\t2\t2",
+        "TAP\t3\t96\t4\t2",
+        "TAP\t3\t144\t6\t2",
+        "TAP\t3\t192\t8\t2",
+        // a chord of two notes.
+        "TAP\t5\t0\t0\t2",
+        "TAP\t5\t0\t8\t2",
+        "TAP\t5\t48\t0\t2",
+        "TAP\t5\t48\t8\t2",
+        "TAP\t5\t96\t0\t2",
+        "TAP\t5\t96\t8\t2",
+        "TAP\t5\t144\t0\t2",
+        "TAP\t5\t144\t8\t2",
+        // taps over a Slide, not counting its head.
+        "SLD\t7\t0\t12\t4\t384\t12\t4\tSLD",
+        "TAP\t7\t48\t0\t2",
+        "TAP\t7\t96\t4\t2",
+        "TAP\t7\t144\t2\t2",
+        "TAP\t7\t192\t6\t2",
+        // ExTaps, one of them heading a slide chain with a curve.
+        "CHR\t9\t0\t0\t2\tUP",
+        "SXC\t9\t48\t8\t2\t48\t6\t2\tSLD\tUP",
+        "CHR\t9\t96\t4\t2\tUP",
+        "CHR\t9\t144\t12\t2\tUP",
+    ];
+    chart.notes = notes.iter().map(|x| x.parse().unwrap()).collect();
+
+    let patterns = chart
+        .patterns()
+        .iter()
+        .map(|x| (x.kind, x.start_tick, x.end_tick, x.notes))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        patterns,
+        [
+            (PatternKind::Jack, 384, 384 + 144, 4),
+            (PatternKind::Trill, 384 + 144, 384 + 288, 4),
+            (PatternKind::Stairs, 3 * 384, 3 * 384 + 192, 5),
+            (PatternKind::Chord, 5 * 384, 5 * 384 + 144, 8),
+            (PatternKind::TapSlide, 7 * 384 + 48, 7 * 384 + 192, 4),
+            (PatternKind::ExTapRain, 9 * 384, 9 * 384 + 144, 4),
+        ]
+    );
+
+    // names line up in columns.
+    assert_eq!(format!("{:<10}|", PatternKind::Trill), "trill     |");
+}
+
+#[test]
+fn test_hands() {
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let mut chart = ChuniChart::parse(chart_string).unwrap();
+
+    let hands = chart.hands();
+    assert!(hands.conflicts.is_empty());
+    for (note, hand) in chart.notes.iter().zip(&hands.hands) {
+        let played = note.is_ground() && !matches!(note, NoteType::Mine(_));
+        assert_eq!(hand.is_some(), played, "{}", note);
+    }
+
+    let notes = [
+        // a Hold on the left, with taps on the right while it is held.
+        "HLD\t1\t0\t0\t2\t192",
+        "TAP\t1\t48\t12\t2",
+        "TAP\t1\t96\t14\t2",
+        // three taps too far apart for two hands.
+        "TAP\t2\t0\t0\t2",
+        "TAP\t2\t0\t7\t2",
+        "TAP\t2\t0\t14\t2",
+    ];
+    chart.notes = notes.iter().map(|x| x.parse().unwrap()).collect();
+
+    let hands = chart.hands();
+    assert_eq!(
+        hands.hands[..3],
+        [Some(Hand::Left), Some(Hand::Right), Some(Hand::Right)]
+    );
+    assert_eq!(hands.conflicts.len(), 1);
+    assert_eq!(hands.conflicts[0].measure, 2);
+    assert_eq!(hands.conflicts[0].hands, 3);
+    assert_eq!(hands.hands[3..].iter().flatten().count(), 2);
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn test_render() {
+    use nai_rs::render::{render_svg, RenderOptions};
+
+    let chart_string: String = fs::read_to_string("tests/zegallta_master.c2s").unwrap();
+    let chart = ChuniChart::parse(chart_string).unwrap();
+    let svg = render_svg(&chart, &RenderOptions::default());
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(">238.000<"));
+}